@@ -1,30 +1,65 @@
 extern crate proc_macro;
 
 use proc_macro::TokenStream;
-use proc_macro2::TokenStream as TokenStream2;
-use quote::{quote, format_ident};
+use proc_macro2::{Delimiter, Spacing, TokenStream as TokenStream2, TokenTree};
+use quote::{quote, format_ident, ToTokens};
 use syn::spanned::Spanned;
-use syn::parse::{Parse, ParseStream, Result};
+use syn::parse::{Parse, ParseStream, Parser, Result};
 use syn::*;
 
+enum ShouldFail {
+    Any,
+    Containing(LitStr),
+    Matching(TokenStream2),
+}
+
+struct Case {
+    /// The parenthesised case as written, for errors about it as a whole.
+    tokens: TokenStream2,
+    label: Option<LitStr>,
+    values: Vec<TokenStream2>,
+}
+
 struct Opts {
     name: Option<LitStr>,
+    cases: Vec<Case>,
+    types: Option<Vec<Type>>,
+    property: bool,
+    property_cases: Option<LitInt>,
+    seed: Option<LitInt>,
+    executor: Option<Path>,
+    timeout_ms: Option<LitInt>,
+    serial: bool,
+    resources: Vec<LitStr>,
     skip: Option<Option<LitStr>>,
     disable: bool,
     no_compile: bool,
-    should_fail: bool,
-    should_panic: bool,
+    should_fail: Option<ShouldFail>,
+    should_panic: Option<Option<LitStr>>,
+    should_abort: bool,
+    should_exit: Option<LitInt>,
 }
 
 impl Default for Opts {
     fn default() -> Self {
         Opts {
             name: None,
+            cases: Vec::new(),
+            types: None,
+            property: false,
+            property_cases: None,
+            seed: None,
+            executor: None,
+            timeout_ms: None,
+            serial: false,
+            resources: Vec::new(),
             skip: None,
             no_compile: false,
             disable: false,
-            should_fail: false,
-            should_panic: false,
+            should_fail: None,
+            should_panic: None,
+            should_abort: false,
+            should_exit: None,
         }
     }
 }
@@ -45,10 +80,47 @@ impl Opts {
                         None
                     });
                 },
+                Some(id) if id == "property" => self.property = true,
+                Some(id) if id == "cases" => {
+                    input.parse::<Token![=]>()?;
+                    self.property_cases = Some(input.parse()?)
+                },
+                Some(id) if id == "seed" => {
+                    input.parse::<Token![=]>()?;
+                    self.seed = Some(input.parse()?)
+                },
+                Some(id) if id == "executor" => {
+                    input.parse::<Token![=]>()?;
+                    self.executor = Some(input.parse()?)
+                },
+                Some(id) if id == "timeout_ms" => {
+                    input.parse::<Token![=]>()?;
+                    self.timeout_ms = Some(input.parse()?)
+                },
+                Some(id) if id == "serial" => self.serial = true,
+                Some(id) if id == "resource" => {
+                    input.parse::<Token![=]>()?;
+                    self.resources.push(input.parse()?)
+                },
                 Some(id) if id == "disable" => self.disable = true,
                 Some(id) if id == "no_compile" => self.no_compile = true,
-                Some(id) if id == "should_fail" => self.should_fail = true,
-                Some(id) if id == "should_panic" => self.should_panic = true,
+                Some(id) if id == "should_fail" => self.should_fail = Some(parse_should_fail(input)?),
+                Some(id) if id == "should_panic" => self.should_panic = Some(parse_should_panic(input)?),
+                Some(id) if id == "should_abort" => self.should_abort = true,
+                Some(id) if id == "should_exit" => {
+                    input.parse::<Token![=]>()?;
+                    self.should_exit = Some(input.parse()?)
+                },
+                Some(id) if id == "case" => self.cases.push(parse_case(input)?),
+                Some(id) if id == "types" => {
+                    let content;
+                    parenthesized!(content in input);
+                    let types = content.parse_terminated::<Type, Token![,]>(Type::parse)?;
+                    if types.is_empty() {
+                        return Err(Error::new_spanned(id, "expected at least one type"))
+                    }
+                    self.types = Some(types.into_iter().collect())
+                },
                 Some(id) =>
                     return Err(Error::new_spanned(id, "unrecognized option")),
             }
@@ -62,6 +134,90 @@ impl Opts {
     }
 }
 
+/// Parses what follows `should_panic`: nothing, `= "msg"`, or `(expected = "msg")`.
+fn parse_should_panic(input: ParseStream) -> Result<Option<LitStr>> {
+    if input.peek(token::Paren) {
+        let content;
+        parenthesized!(content in input);
+        let id: Ident = content.parse()?;
+        if id != "expected" {
+            return Err(Error::new_spanned(id, "expected `expected = \"...\"`"))
+        }
+        content.parse::<Token![=]>()?;
+        Ok(Some(content.parse()?))
+    } else if input.parse::<Option<Token![=]>>()?.is_some() {
+        Ok(Some(input.parse()?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Parses what follows `should_fail`: nothing, `= "msg"`, `(expected = "msg")`, or `(matches = PATTERN)`.
+fn parse_should_fail(input: ParseStream) -> Result<ShouldFail> {
+    if input.peek(token::Paren) {
+        let content;
+        parenthesized!(content in input);
+        let id: Ident = content.parse()?;
+        content.parse::<Token![=]>()?;
+        match () {
+            _ if id == "expected" => Ok(ShouldFail::Containing(content.parse()?)),
+            _ if id == "matches" => {
+                let pattern: TokenStream2 = content.parse()?;
+                match pattern.is_empty() {
+                    true => Err(Error::new_spanned(id, "expected a pattern")),
+                    false => Ok(ShouldFail::Matching(pattern)),
+                }
+            },
+            _ => Err(Error::new_spanned(id, "expected `expected = \"...\"` or `matches = PATTERN`")),
+        }
+    } else if input.parse::<Option<Token![=]>>()?.is_some() {
+        Ok(ShouldFail::Containing(input.parse()?))
+    } else {
+        Ok(ShouldFail::Any)
+    }
+}
+
+/// Parses `(value, ...)`, where one of the values may instead be a `name = "label"`.
+///
+/// Values are split on top-level commas, since `syn` is built without full expression support.
+fn parse_case(input: ParseStream) -> Result<Case> {
+    let tokens = input.fork().parse::<TokenTree>()?.into();
+    let content;
+    parenthesized!(content in input);
+    let mut case = Case {
+        tokens,
+        label: None,
+        values: Vec::new(),
+    };
+    while !content.is_empty() {
+        if content.peek(Ident) && content.peek2(Token![=]) && !content.peek2(Token![==]) {
+            let id: Ident = content.parse()?;
+            if id != "name" {
+                return Err(Error::new_spanned(id, "expected `name = \"...\"`"))
+            }
+            content.parse::<Token![=]>()?;
+            case.label = Some(content.parse()?);
+        } else {
+            let mut value = TokenStream2::new();
+            while !content.is_empty() && !content.peek(Token![,]) {
+                value.extend(Some(content.parse::<TokenTree>()?));
+            }
+            if value.is_empty() {
+                return Err(content.error("expected a value"))
+            }
+            case.values.push(value);
+        }
+
+        if content.parse::<Option<Token![,]>>()?.is_none() {
+            break
+        }
+    }
+    match content.is_empty() {
+        true => Ok(case),
+        false => Err(content.error("expected `,`")),
+    }
+}
+
 impl Parse for Opts {
     fn parse(input: ParseStream) -> Result<Self> {
         let mut opts = Self::default();
@@ -85,10 +241,117 @@ impl Parse for FnArg {
     }
 }
 
+/// Writes tokens out the way they'd usually be written by hand, for a test name or message.
+///
+/// `TokenStream`'s own `Display` doesn't promise any particular spacing, so this decides it from
+/// the tokens themselves.
+fn print_tokens(tokens: TokenStream2) -> String {
+    /// What the last thing written asks of the space before the next.
+    enum Gap {
+        None,
+        /// Only if the next is an identifier or literal too.
+        Word,
+        Always,
+    }
+
+    let mut out = String::new();
+    let mut gap = Gap::None;
+    let mut op = String::new();
+    for token in tokens {
+        let (text, spaced, next) = match token {
+            TokenTree::Ident(ident) => (ident.to_string(), !matches!(gap, Gap::None), Gap::Word),
+            TokenTree::Literal(literal) => (literal.to_string(), !matches!(gap, Gap::None), Gap::Word),
+            // a lifetime's quote, which joins onto its name
+            TokenTree::Punct(punct) if punct.as_char() == '\'' => ("'".to_owned(), matches!(gap, Gap::Always), Gap::None),
+            TokenTree::Punct(punct) if punct.spacing() == Spacing::Joint => {
+                op.push(punct.as_char());
+                continue
+            },
+            TokenTree::Punct(punct) => {
+                op.push(punct.as_char());
+                let op = std::mem::take(&mut op);
+                match &op[..] {
+                    "," | ";" | ":" => (op, false, Gap::Always),
+                    "=" | "=>" | "|" | "@" | "+" | "==" | "!=" | "<=" | ">=" | "&&" | "||" => (op, true, Gap::Always),
+                    _ => (op, matches!(gap, Gap::Always), Gap::None),
+                }
+            },
+            TokenTree::Group(group) => {
+                let inner = print_tokens(group.stream());
+                match group.delimiter() {
+                    Delimiter::Parenthesis => (format!("({})", inner), matches!(gap, Gap::Always), Gap::Word),
+                    Delimiter::Bracket => (format!("[{}]", inner), matches!(gap, Gap::Always), Gap::Word),
+                    Delimiter::Brace if inner.is_empty() => ("{}".to_owned(), true, Gap::Word),
+                    Delimiter::Brace => (format!("{{ {} }}", inner), true, Gap::Word),
+                    Delimiter::None => (inner, !matches!(gap, Gap::None), Gap::Word),
+                }
+            },
+        };
+        if spaced && !out.is_empty() {
+            out.push(' ');
+        }
+        out.push_str(&text);
+        gap = next;
+    }
+    out
+}
+
+/// Names a type for a test name, from its syntax where that's simple enough.
+fn type_name(ty: &Type) -> String {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => {
+            let segments = path.path.segments.iter().map(|segment| match &segment.arguments {
+                PathArguments::None => segment.ident.to_string(),
+                PathArguments::AngleBracketed(args) => {
+                    let args = args.args.iter().map(|arg| match arg {
+                        GenericArgument::Type(ty) => type_name(ty),
+                        arg => print_tokens(arg.to_token_stream()),
+                    }).collect::<Vec<_>>();
+                    format!("{}<{}>", segment.ident, args.join(", "))
+                },
+                PathArguments::Parenthesized(..) => print_tokens(segment.to_token_stream()),
+            }).collect::<Vec<_>>();
+            match path.path.leading_colon {
+                Some(..) => format!("::{}", segments.join("::")),
+                None => segments.join("::"),
+            }
+        },
+        Type::Reference(r) => {
+            let lifetime = r.lifetime.as_ref().map(|lifetime| format!("{} ", lifetime)).unwrap_or_default();
+            let mutability = if r.mutability.is_some() { "mut " } else { "" };
+            format!("&{}{}{}", lifetime, mutability, type_name(&r.elem))
+        },
+        Type::Slice(slice) => format!("[{}]", type_name(&slice.elem)),
+        Type::Array(array) => format!("[{}; {}]", type_name(&array.elem), print_tokens(array.len.to_token_stream())),
+        Type::Tuple(tuple) if tuple.elems.len() == 1 => format!("({},)", type_name(&tuple.elems[0])),
+        Type::Tuple(tuple) => format!("({})", tuple.elems.iter().map(type_name).collect::<Vec<_>>().join(", ")),
+        Type::Paren(paren) => format!("({})", type_name(&paren.elem)),
+        Type::Group(group) => type_name(&group.elem),
+        ty => print_tokens(ty.to_token_stream()),
+    }
+}
+
+/// Whether an argument is the `TestContext`, or a reference to it, rather than a fixture.
+///
+/// Only the type's name says so, which is why the context has to be the first argument: an alias
+/// for it anywhere else would quietly be taken for a fixture.
+fn is_context(ty: &Type) -> bool {
+    let ty = match ty {
+        Type::Reference(r) => &*r.elem,
+        ty => ty,
+    };
+    match ty {
+        Type::Path(path) => path.path.segments.last().map(|segment| segment.ident == "TestContext").unwrap_or(false),
+        _ => false,
+    }
+}
+
 struct TestFn {
     fn_attrs: Vec<Attribute>,
     vis: Visibility,
+    asyncness: Option<Token![async]>,
     ident: Ident,
+    generics: Generics,
     args: Vec<FnArg>,
     ret_ty: Option<Type>,
     body: TokenStream2,
@@ -98,8 +361,10 @@ impl Parse for TestFn {
     fn parse(input: ParseStream) -> Result<Self> {
         let fn_attrs = input.call(Attribute::parse_outer)?;
         let vis: Visibility = input.parse()?;
+        let asyncness = input.parse()?;
         input.parse::<Token![fn]>()?;
         let ident: Ident = input.parse()?;
+        let mut generics: Generics = input.parse()?;
 
         let argument_list;
         parenthesized!(argument_list in input);
@@ -122,6 +387,7 @@ impl Parse for TestFn {
         } else {
             None
         };
+        generics.where_clause = input.parse()?;
 
         let body;
         braced!(body in input);
@@ -130,7 +396,9 @@ impl Parse for TestFn {
         Ok(TestFn {
             fn_attrs,
             vis,
+            asyncness,
             ident,
+            generics,
             args,
             ret_ty,
             body,
@@ -144,12 +412,15 @@ pub fn test(opts: TokenStream, input: TokenStream) -> TokenStream {
     let TestFn {
         fn_attrs,
         vis,
+        asyncness,
         ident,
+        generics,
         args,
         ret_ty,
         body,
     } = parse_macro_input!(input as TestFn);
 
+    let mut attr_errors = Vec::new();
     let fn_attrs: Vec<_> = fn_attrs.into_iter().filter(|attr| match attr.tokens.is_empty() {
         true if attr.path.is_ident("ignore") => {
             if opts.skip.is_none() {
@@ -157,8 +428,18 @@ pub fn test(opts: TokenStream, input: TokenStream) -> TokenStream {
             }
             false
         },
-        true if attr.path.is_ident("should_panic") => {
-            opts.should_panic = true;
+        false if attr.path.is_ident("case") => {
+            match parse_case.parse2(attr.tokens.clone()) {
+                Ok(case) => opts.cases.push(case),
+                Err(e) => attr_errors.push(e.to_compile_error()),
+            }
+            false
+        },
+        _ if attr.path.is_ident("should_panic") => {
+            match parse_should_panic.parse2(attr.tokens.clone()) {
+                Ok(expected) => opts.should_panic = Some(expected),
+                Err(e) => attr_errors.push(e.to_compile_error()),
+            }
             false
         },
         true if attr.path.is_ident("test") => {
@@ -179,32 +460,235 @@ pub fn test(opts: TokenStream, input: TokenStream) -> TokenStream {
         ::mintest
     };
 
-    let test_ident = format_ident!("{}__test", ident);
-    let test_fn_ident = format_ident!("{}__test_fn", ident);
     let test_status = match (opts.disable, &opts.skip) {
         (true, _) => quote! { #path::TestStatus::Disable },
         (false, Some(None)) => quote! { #path::TestStatus::Skip(#path::internal::core::option::Option::None) },
         (false, Some(Some(reason))) => quote! { #path::TestStatus::Skip(#path::internal::core::option::Option::Some(#reason)) },
         (false, None) => quote! { #path::TestStatus::Enable },
     };
-    let test_name = opts.name.map(|n| n.value()).unwrap_or(ident.to_string());
+    let test_name = opts.name.as_ref().map(|n| n.value()).unwrap_or(ident.to_string());
     let context_ident: Ident = parse_quote!(__test_context);
-    let context_ident = arg_names.get(0).cloned().unwrap_or(&context_ident);
     let context_args = quote!(#context_ident: #path::TestContext);
-    let test_expected = match (opts.should_panic, opts.should_fail) {
-        (true, _) => quote! { #path::TestExpected::Panic },
-        (false, true) => quote! { #path::TestExpected::Fail },
-        (false, false) => quote! { #path::TestExpected::Success },
+    let test_expected = match (&opts.should_panic, &opts.should_fail, opts.should_abort, &opts.should_exit) {
+        (None, None, true, None) => quote! { #path::TestExpected::Abort },
+        (None, None, false, Some(code)) => quote! { #path::TestExpected::Exit(#code) },
+        (_, _, true, _) | (_, _, _, Some(_)) => {
+            attr_errors.push(Error::new(ident.span(), "`should_abort` and `should_exit` can't be combined with other expectations").to_compile_error());
+            quote! { #path::TestExpected::Success }
+        },
+        (Some(Some(expected)), ..) => quote! { #path::TestExpected::PanicWith(#expected) },
+        (Some(None), ..) => quote! { #path::TestExpected::Panic },
+        (None, Some(ShouldFail::Any), ..) => quote! { #path::TestExpected::Fail },
+        (None, Some(ShouldFail::Containing(expected)), ..) => quote! { #path::TestExpected::FailWith(#expected) },
+        (None, Some(ShouldFail::Matching(pattern)), ..) => {
+            let pattern = print_tokens(pattern.clone());
+            quote! { #path::TestExpected::FailMatching(#pattern) }
+        },
+        (None, None, ..) => quote! { #path::TestExpected::Success },
+    };
+
+    for param in &generics.params {
+        if let GenericParam::Lifetime(..) | GenericParam::Const(..) = param {
+            attr_errors.push(Error::new_spanned(param, "tests can only be generic over types, given by `types(...)`").to_compile_error());
+        }
+    }
+    let type_param = generics.type_params().next().map(|param| &param.ident);
+    let types = match (type_param, &opts.types) {
+        (Some(_), Some(types)) if generics.type_params().count() == 1 => types.iter().map(Some).collect(),
+        (Some(_), Some(_)) => {
+            attr_errors.push(Error::new_spanned(&generics, "`types(...)` only supports a single type parameter").to_compile_error());
+            Vec::new()
+        },
+        (Some(_), None) => {
+            attr_errors.push(Error::new_spanned(&generics, "generic tests need `types(...)` to instantiate them with").to_compile_error());
+            Vec::new()
+        },
+        (None, Some(types)) => {
+            attr_errors.push(Error::new_spanned(&types[0], "`types(...)` needs a generic test").to_compile_error());
+            Vec::new()
+        },
+        (None, None) => vec![None],
     };
-    let test = quote! {
-        #path::Test {
-            status: #test_status,
-            name: #test_name,
-            test: #path::TestFn::Static(#test_fn_ident),
-            expected: #test_expected,
+    let turbofish = type_param.map(|param| quote!(::<#param>));
+    if let (None, Some(executor)) = (&asyncness, &opts.executor) {
+        attr_errors.push(Error::new_spanned(executor, "only async tests take an `executor`").to_compile_error());
+    }
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+    // one registered test per case, or just the one without any
+    let no_case = [Case {
+        tokens: TokenStream2::new(),
+        label: None,
+        values: Vec::new(),
+    }];
+    let cases = match opts.cases.is_empty() {
+        true => &no_case[..],
+        false => &opts.cases[..],
+    };
+    if !opts.property {
+        if let Some(lit) = opts.property_cases.as_ref().or(opts.seed.as_ref()) {
+            attr_errors.push(Error::new_spanned(lit, "only property tests take `cases` or `seed`").to_compile_error());
         }
+    }
+    let serial = opts.serial;
+    let resources = &opts.resources;
+    let test_timeout = match &opts.timeout_ms {
+        Some(ms) => quote!(#path::internal::core::option::Option::Some(#path::internal::core::time::Duration::from_millis(#ms))),
+        None => quote!(#path::internal::core::option::Option::None),
+    };
+    let property_cases = opts.property_cases.as_ref().map(|cases| quote!(#cases)).unwrap_or(quote!(256));
+    let property_seed = match &opts.seed {
+        Some(seed) => quote!(#path::internal::core::option::Option::Some(#seed)),
+        None => quote!(#path::internal::core::option::Option::None),
     };
 
+    for fn_arg in args.iter().skip(1).filter(|fn_arg| is_context(&fn_arg.ty)) {
+        attr_errors.push(Error::new_spanned(&fn_arg.ty, "the `TestContext` has to be the test's first argument").to_compile_error());
+    }
+    if let Some(Type::Reference(r)) = args.first().map(|fn_arg| &fn_arg.ty).filter(|ty| is_context(ty)) {
+        if r.mutability.is_some() {
+            attr_errors.push(Error::new_spanned(r, "the `TestContext` is taken by value or `&` reference").to_compile_error());
+        }
+    }
+    let params = args.iter().filter(|fn_arg| !is_context(&fn_arg.ty)).count();
+    let mut expanded_test = TokenStream2::new();
+    for (n, case) in cases.iter().enumerate() {
+        if case.values.len() > params {
+            attr_errors.push(Error::new_spanned(&case.values[params], "too many values for this test's arguments").to_compile_error());
+            continue
+        }
+        // only a property test has anything to fill in the rest with
+        if case.values.len() < params && !opts.cases.is_empty() && !opts.property {
+            let message = format!("this test takes {} values, but the case only gives {}", params, case.values.len());
+            attr_errors.push(Error::new_spanned(&case.tokens, message).to_compile_error());
+            continue
+        }
+
+        let (case_ident, case_name) = match (opts.cases.is_empty(), &case.label) {
+            (true, _) => (ident.clone(), None),
+            (false, Some(label)) => (format_ident!("{}__case_{}", ident, n + 1), Some(label.value())),
+            (false, None) => (format_ident!("{}__case_{}", ident, n + 1), Some(format!("case_{}", n + 1))),
+        };
+        let test_fn_ident = format_ident!("{}__test_fn", case_ident);
+
+        let mut values = case.values.iter();
+        let mut fixtures = Vec::new();
+        let mut inputs = Vec::new();
+        let call_args = args.iter().enumerate().map(|(i, fn_arg)| {
+            match &fn_arg.ty {
+                Type::Reference(..) if is_context(&fn_arg.ty) => return quote!(&#context_ident),
+                ty if is_context(ty) => return quote!(#context_ident.clone()),
+                _ => (),
+            }
+            if let Some(value) = values.next() {
+                return quote!(#value)
+            }
+
+            // property tests generate what's left, everything else builds fixtures
+            let fixture_ident = match opts.property {
+                true => format_ident!("__input_{}", i),
+                false => format_ident!("__fixture_{}", i),
+            };
+            let fixture_name = fn_arg.ident.to_string();
+            let (ty, mutability, arg) = match &fn_arg.ty {
+                Type::Reference(r) if r.mutability.is_some() => (&*r.elem, quote!(mut), quote!(&mut #fixture_ident)),
+                Type::Reference(r) => (&*r.elem, quote!(), quote!(&#fixture_ident)),
+                ty => (ty, quote!(), quote!(#fixture_ident)),
+            };
+            if opts.property {
+                inputs.push((quote!(#mutability #fixture_ident), ty));
+                return arg
+            }
+            fixtures.push(quote! {
+                let #mutability #fixture_ident = match <#ty as #path::Fixture>::build(&#context_ident) {
+                    #path::internal::core::result::Result::Ok(fixture) => fixture,
+                    #path::internal::core::result::Result::Err(e) =>
+                        return #path::internal::core::result::Result::Err(#path::TestError::setup(#fixture_name, e)),
+                };
+            });
+            arg
+        }).collect::<Vec<_>>();
+
+        let test_call = quote! { #ident #turbofish(#(#call_args),*) };
+        let test_call = match (&asyncness, &opts.executor) {
+            (Some(_), Some(executor)) => quote! { #executor(#test_call) },
+            (Some(_), None) => quote! { #path::block_on(#test_call) },
+            (None, _) => test_call,
+        };
+        let test_call = match &opts.should_fail {
+            Some(ShouldFail::Matching(pattern)) => quote! {
+                match #test_call {
+                    #path::internal::core::result::Result::Ok(_) => #path::internal::core::result::Result::Ok(()),
+                    #[allow(unused_variables)]
+                    #path::internal::core::result::Result::Err(e) => #path::internal::core::result::Result::Err(match &e {
+                        #pattern => #path::TestError::format(#path::internal::core::format_args!("{:?}", e)),
+                        _ => #path::TestError::Mismatch(#path::ErrorMessage::format(#path::internal::core::format_args!("{:?}", e))),
+                    }),
+                }
+            },
+            _ => quote! { #path::IntoTestResult::into_test_result(#test_call) },
+        };
+        let test_call = match opts.property {
+            true => {
+                if inputs.is_empty() {
+                    attr_errors.push(Error::new(ident.span(), "property tests need an argument to generate").to_compile_error());
+                }
+                let (input_pats, input_tys): (Vec<_>, Vec<_>) = inputs.into_iter().unzip();
+                quote! {
+                    #path::property::check::<(#(#input_tys,)*), _>(&#context_ident, #property_cases, #property_seed, |(#(#input_pats,)*)| #test_call)
+                }
+            },
+            false => test_call,
+        };
+        expanded_test.extend(quote! {
+            #[allow(non_snake_case)]
+            fn #test_fn_ident #impl_generics(#context_args) -> #path::TestResult #where_clause {
+                #(#fixtures)*
+                #test_call
+            }
+        });
+
+        for (k, ty) in types.iter().enumerate() {
+            let (test_ident, test_fn, test_name) = match ty {
+                Some(ty) => (
+                    format_ident!("{}__type_{}__test", case_ident, k + 1),
+                    quote!(#test_fn_ident::<#ty>),
+                    format!("{}::<{}>", test_name, type_name(ty)),
+                ),
+                None => (format_ident!("{}__test", case_ident), quote!(#test_fn_ident), test_name.clone()),
+            };
+            let test_name = match &case_name {
+                Some(case_name) => format!("{}::{}", test_name, case_name),
+                None => test_name,
+            };
+            let test = quote! {
+                #path::Test {
+                    status: #test_status,
+                    name: #test_name,
+                    module_path: #path::internal::core::module_path!(),
+                    test: #path::TestFn::Static(#test_fn),
+                    expected: #test_expected,
+                    timeout: #test_timeout,
+                    serial: #serial,
+                    resources: &[#(#resources),*],
+                }
+            };
+
+            let (test_attr, test_def) = match () {
+                #[cfg(any(not(feature = "unstable-test"), feature = "test"))]
+                _ => (quote! { #[#path::internal::distributed_slice(#path::TESTS)] }, quote!(static)),
+                #[cfg(all(feature = "unstable-test", not(feature = "test")))]
+                _ => (quote! { #[test_case] }, quote!(const)),
+            };
+
+            expanded_test.extend(quote! {
+                #test_attr
+                #[allow(non_upper_case_globals)]
+                #test_def #test_ident: #path::Test = #test;
+            });
+        }
+    }
+
     let body = match (opts.no_compile, opts.disable, opts.skip) {
         (true, false, None) => {
             Error::new_spanned(body, "no_compile test not disabled or skipped").to_compile_error()
@@ -217,27 +701,9 @@ pub fn test(opts: TokenStream, input: TokenStream) -> TokenStream {
         },
     };
 
-    let (test_attr, test_def) = match () {
-        #[cfg(any(not(feature = "unstable-test"), feature = "test"))]
-        _ => (quote! { #[#path::internal::distributed_slice(#path::TESTS)] }, quote!(static)),
-        #[cfg(all(feature = "unstable-test", not(feature = "test")))]
-        _ => (quote! { #[test_case] }, quote!(const)),
-    };
-
-    let expanded_test = quote! {
-        #[allow(non_snake_case)]
-        fn #test_fn_ident(#context_args) -> #path::TestResult {
-            #path::IntoTestResult::into_test_result(#ident(#(#arg_names),*))
-        }
-
-        #test_attr
-        #[allow(non_upper_case_globals)]
-        #test_def #test_ident: #path::Test = #test;
-    };
-
     let expanded_fn = quote! {
         #(#fn_attrs)*
-        #vis fn #ident(#(#arg_names: #arg_types),*) #ret_ty
+        #vis #asyncness fn #ident #impl_generics(#(#arg_names: #arg_types),*) #ret_ty #where_clause
     };
 
     let expanded = match () {
@@ -259,5 +725,91 @@ pub fn test(opts: TokenStream, input: TokenStream) -> TokenStream {
         },
     };
 
-    TokenStream::from(expanded)
+    TokenStream::from(quote! {
+        #(#attr_errors)*
+        #expanded
+    })
+}
+
+fn hook(slice: &str, takes_context: bool, opts: TokenStream, input: TokenStream) -> TokenStream {
+    let opts = TokenStream2::from(opts);
+    if !opts.is_empty() {
+        return TokenStream::from(Error::new_spanned(opts, "unrecognized option").to_compile_error())
+    }
+    let TestFn {
+        fn_attrs,
+        vis,
+        asyncness,
+        ident,
+        generics,
+        args,
+        ret_ty,
+        body,
+    } = parse_macro_input!(input as TestFn);
+    if !generics.params.is_empty() {
+        return TokenStream::from(Error::new_spanned(generics, "hooks can't be generic").to_compile_error())
+    }
+
+    let path = quote! {
+        ::mintest
+    };
+
+    let slice = format_ident!("{}", slice);
+    let hook_ident = format_ident!("{}__hook", ident);
+    let hook_fn_ident = format_ident!("{}__hook_fn", ident);
+    let hook_name = ident.to_string();
+    let arg_names = args.iter().map(|fn_arg| &fn_arg.ident).collect::<Vec<_>>();
+    let arg_types = args.iter().map(|fn_arg| &fn_arg.ty).collect::<Vec<_>>();
+    let ret_ty = ret_ty.map(|ret| quote!(-> #ret));
+
+    let (hook_ty, hook_args, hook_call) = match (takes_context, &arg_names[..]) {
+        (true, []) => (quote!(TestHook), quote!(_: &#path::TestContext), quote!(#ident())),
+        (true, [context]) => (quote!(TestHook), quote!(#context: &#path::TestContext), quote!(#ident(#context))),
+        (false, []) => (quote!(RunHook), quote!(), quote!(#ident())),
+        (true, _) => return TokenStream::from(Error::new_spanned(&args[1].ident, "expected at most a `&TestContext` argument").to_compile_error()),
+        (false, _) => return TokenStream::from(Error::new_spanned(&args[0].ident, "expected no arguments").to_compile_error()),
+    };
+    let hook_call = match asyncness {
+        Some(_) => quote!(#path::block_on(#hook_call)),
+        None => hook_call,
+    };
+
+    TokenStream::from(quote! {
+        #(#fn_attrs)*
+        #vis #asyncness fn #ident(#(#arg_names: #arg_types),*) #ret_ty {
+            #[allow(non_snake_case)]
+            fn #hook_fn_ident(#hook_args) -> #path::TestResult {
+                #path::IntoTestResult::into_test_result(#hook_call)
+            }
+
+            #[#path::internal::distributed_slice(#path::#slice)]
+            #[allow(non_upper_case_globals)]
+            static #hook_ident: #path::#hook_ty = #path::#hook_ty {
+                name: #hook_name,
+                hook: #hook_fn_ident,
+            };
+
+            #body
+        }
+    })
+}
+
+#[proc_macro_attribute]
+pub fn setup(opts: TokenStream, input: TokenStream) -> TokenStream {
+    hook("SETUP", true, opts, input)
+}
+
+#[proc_macro_attribute]
+pub fn teardown(opts: TokenStream, input: TokenStream) -> TokenStream {
+    hook("TEARDOWN", true, opts, input)
+}
+
+#[proc_macro_attribute]
+pub fn before_all(opts: TokenStream, input: TokenStream) -> TokenStream {
+    hook("BEFORE_ALL", false, opts, input)
+}
+
+#[proc_macro_attribute]
+pub fn after_all(opts: TokenStream, input: TokenStream) -> TokenStream {
+    hook("AFTER_ALL", false, opts, input)
 }
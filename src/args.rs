@@ -0,0 +1,257 @@
+use std::{env, io, process};
+use std::time::SystemTime;
+use std::io::Write as _;
+use core::fmt::Write as _;
+use core::str::FromStr;
+use core::time::Duration;
+
+use super::{Hooks, OutputFormat, Platform, Rng, RunConfig, RunIgnored, Test, TestOrder, TestStatus, run_tests_with, run_tests_reporting};
+use super::order;
+use super::isolate::{self, ChildReporter};
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "pretty" => Ok(OutputFormat::Pretty),
+            "terse" => Ok(OutputFormat::Terse),
+            "tap" => Ok(OutputFormat::Tap),
+            "junit" => Ok(OutputFormat::Junit),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!("unsupported output format `{}`", s)),
+        }
+    }
+}
+
+/// The subset of libtest's command line that mintest understands.
+#[derive(Debug, Clone, Default)]
+pub struct Args {
+    pub filters: Vec<String>,
+    pub skip: Vec<String>,
+    pub exact: bool,
+    pub list: bool,
+    pub ignored: RunIgnored,
+    pub nocapture: bool,
+    pub test_threads: Option<usize>,
+    pub format: OutputFormat,
+    pub timeout: Option<Duration>,
+    pub abort_on_timeout: bool,
+    pub isolate: bool,
+    pub shuffle: bool,
+    pub shuffle_seed: Option<u64>,
+    pub help: bool,
+}
+
+const USAGE: &str = "Usage: [OPTIONS] [FILTERS...]
+
+Options:
+        --include-ignored
+                        Run skipped tests as well as enabled ones
+        --ignored       Run only skipped tests
+        --exact         Exactly match filters rather than by substring
+        --skip FILTER   Skip tests whose names contain FILTER (may be repeated)
+        --list          List all tests instead of running them
+        --nocapture     Accepted for compatibility; output is never captured
+        --test-threads n_threads
+                        Number of threads used for running tests
+        --format pretty|terse|tap|junit|json
+                        Configure formatting of output
+        --timeout-ms MS Time out tests that don't set their own timeout after
+                        MS milliseconds
+        --abort-on-timeout
+                        Stop running tests after the first one times out
+        --isolate       Run each test in a process of its own
+        --shuffle[=SEED]
+                        Run tests in a random order, or one reproduced from SEED
+        --shuffle-seed SEED
+                        Alias for --shuffle=SEED
+    -q, --quiet         Alias for --format=terse
+    -h, --help          Display this message
+";
+
+impl Args {
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(env::args().skip(1))
+    }
+
+    pub fn parse<I: IntoIterator<Item=String>>(args: I) -> Result<Self, String> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.find('=') {
+                Some(eq) if arg.starts_with("--") => (&arg[..eq], Some(arg[eq + 1..].to_owned())),
+                _ => (&arg[..], None),
+            };
+            let mut value = |name: &str| inline_value.clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("argument to `{}` missing", name));
+
+            match flag {
+                "-h" | "--help" => parsed.help = true,
+                "--exact" => parsed.exact = true,
+                "--skip" => parsed.skip.push(value(flag)?),
+                "--list" => parsed.list = true,
+                "--ignored" | "--include-ignored" => {
+                    let ignored = match flag {
+                        "--ignored" => RunIgnored::Only,
+                        _ => RunIgnored::Yes,
+                    };
+                    if parsed.ignored != RunIgnored::No && parsed.ignored != ignored {
+                        return Err("`--ignored` and `--include-ignored` are mutually exclusive".into())
+                    }
+                    parsed.ignored = ignored;
+                },
+                "--nocapture" => parsed.nocapture = true,
+                "--test-threads" => {
+                    let threads = value(flag)?;
+                    parsed.test_threads = match threads.parse() {
+                        Ok(0) | Err(..) => return Err(format!("argument for `--test-threads` must be a positive integer, got `{}`", threads)),
+                        Ok(n) => Some(n),
+                    };
+                },
+                "--format" => parsed.format = value(flag)?.parse()?,
+                "--timeout-ms" => {
+                    let timeout = value(flag)?;
+                    parsed.timeout = match timeout.parse() {
+                        Ok(0) | Err(..) => return Err(format!("argument for `--timeout-ms` must be a positive integer, got `{}`", timeout)),
+                        Ok(ms) => Some(Duration::from_millis(ms)),
+                    };
+                },
+                "--abort-on-timeout" => parsed.abort_on_timeout = true,
+                "--isolate" => parsed.isolate = true,
+                "--shuffle" | "--shuffle-seed" => {
+                    parsed.shuffle = true;
+                    let seed = match flag {
+                        "--shuffle" => inline_value.clone(),
+                        _ => Some(value(flag)?),
+                    };
+                    if let Some(seed) = seed {
+                        parsed.shuffle_seed = match seed.parse() {
+                            Ok(seed) => Some(seed),
+                            Err(..) => return Err(format!("argument for `{}` must be an unsigned integer, got `{}`", flag, seed)),
+                        };
+                    }
+                },
+                "-q" | "--quiet" => parsed.format = OutputFormat::Terse,
+                // accepted for compatibility with cargo and other libtest drivers
+                "--test" | "--show-output" => (),
+                "--color" | "-Z" => {
+                    value(flag)?;
+                },
+                _ if flag.starts_with('-') => return Err(format!("unrecognized option `{}`", flag)),
+                _ => parsed.filters.push(arg),
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    pub fn with_config<R, F: FnOnce(&RunConfig) -> R>(&self, f: F) -> R {
+        let filters: Vec<&str> = self.filters.iter().map(|s| &s[..]).collect();
+        let skip: Vec<&str> = self.skip.iter().map(|s| &s[..]).collect();
+        f(&RunConfig {
+            filters: &filters,
+            skip: &skip,
+            exact: self.exact,
+            ignored: self.ignored,
+            format: self.format,
+            clock: None,
+            hooks: Hooks::default(),
+            timeout: self.timeout,
+            abort_on_timeout: self.abort_on_timeout,
+            watchdog: None,
+            isolate: self.isolate,
+            test_threads: self.test_threads,
+            order: match (self.shuffle, self.shuffle_seed) {
+                (_, Some(seed)) => TestOrder::Shuffled(seed),
+                (true, None) => TestOrder::Shuffled(random_seed()),
+                (false, None) => TestOrder::Sorted,
+            },
+        })
+    }
+}
+
+/// A seed for `--shuffle` without one, different from run to run.
+fn random_seed() -> u64 {
+    let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+    Rng::new(now.as_nanos() as u64 ^ u64::from(process::id())).next_u64()
+}
+
+fn list_tests<I: AsRef<Test>, T: IntoIterator<Item=I>>(config: &RunConfig, format: OutputFormat, tests: T) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    let mut tests: Vec<I> = tests.into_iter()
+        .filter(|test| !matches!(test.as_ref().status, TestStatus::Disable) && !config.is_filtered(test.as_ref()))
+        .collect();
+    order::sort(&mut tests, config.order);
+    for test in &tests {
+        let test = test.as_ref();
+        write!(stdout, "{}: test", test.name)?;
+        // terse listings are left in the form tools expect of libtest
+        if format == OutputFormat::Pretty && !test.resources.is_empty() {
+            write!(stdout, " (resources: {})", test.resources.join(", "))?;
+        }
+        writeln!(stdout)?;
+    }
+
+    let seed = match config.order {
+        TestOrder::Shuffled(seed) => Some(seed),
+        _ => None,
+    };
+    match (format, seed) {
+        (OutputFormat::Pretty, Some(seed)) => {
+            writeln!(stdout)?;
+            writeln!(stdout, "{} tests, 0 benchmarks (shuffled with seed {})", tests.len(), seed)?;
+        },
+        (OutputFormat::Pretty, None) => {
+            writeln!(stdout)?;
+            writeln!(stdout, "{} tests, 0 benchmarks", tests.len())?;
+        },
+        // kept off stdout, where tools expect only tests
+        (_, Some(seed)) => eprintln!("shuffled with seed {}", seed),
+        (_, None) => (),
+    }
+
+    Ok(())
+}
+
+/// Like `run_tests`, but configured by a libtest-compatible command line.
+pub fn run_tests_from_args<P: Platform + ?Sized, I: AsRef<Test>, T: IntoIterator<Item=I>>(tests: T) -> ! where
+T::IntoIter: Clone {
+    let args = match Args::from_env() {
+        Ok(args) => args,
+        Err(e) => {
+            let _ = writeln!(P::stderr(), "error: {}\n\n{}", e, USAGE);
+            P::abort()
+        },
+    };
+
+    if args.help {
+        let _ = write!(P::stderr(), "{}", USAGE);
+        P::exit()
+    }
+
+    args.with_config(|config| match args.list {
+        true => match list_tests(config, args.format, tests) {
+            Ok(()) => P::exit(),
+            Err(..) => P::abort(),
+        },
+        // re-run by `--isolate` for a single test, whose parent runs the whole-run hooks
+        false if isolate::is_child() => {
+            let config = config.with_platform::<P>();
+            let config = RunConfig {
+                hooks: Hooks {
+                    before_all: &[],
+                    after_all: &[],
+                    ..config.hooks
+                },
+                ..config
+            };
+            let tests = tests.into_iter().filter(|test| isolate::is_target(test.as_ref()));
+            run_tests_reporting::<P, _, _>(&config, &mut ChildReporter, tests)
+        },
+        false => run_tests_with::<P, _, _>(config, tests),
+    })
+}
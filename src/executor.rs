@@ -0,0 +1,27 @@
+use core::future::Future;
+use core::pin::pin;
+use core::ptr;
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+const NOOP_VTABLE: RawWakerVTable = RawWakerVTable::new(
+    |_| RawWaker::new(ptr::null(), &NOOP_VTABLE),
+    |_| (),
+    |_| (),
+    |_| (),
+);
+
+/// Drives a future to completion by polling it in a loop.
+///
+/// This is what `async fn` tests run on unless they name another `executor`. Wakeups are
+/// ignored, so anything waiting on an interrupt or another thread is spun on rather than slept on.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = unsafe { Waker::from_raw(RawWaker::new(ptr::null(), &NOOP_VTABLE)) };
+    let mut context = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output
+        }
+        core::hint::spin_loop();
+    }
+}
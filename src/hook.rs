@@ -0,0 +1,87 @@
+use core::fmt;
+
+use super::{TestContext, TestResult};
+
+/// A `#[setup]` or `#[teardown]` function, run around each test.
+#[derive(Copy, Clone)]
+pub struct TestHook {
+    pub name: &'static str,
+    pub hook: fn(&TestContext) -> TestResult,
+}
+
+/// A `#[before_all]` or `#[after_all]` function, run around the whole test run.
+#[derive(Copy, Clone)]
+pub struct RunHook {
+    pub name: &'static str,
+    pub hook: fn() -> TestResult,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HookKind {
+    Setup,
+    Teardown,
+    BeforeAll,
+    AfterAll,
+}
+
+/// The hooks a run calls; `run_tests` fills these in from the registered slices.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Hooks<'a> {
+    pub setup: &'a [TestHook],
+    /// Run after every test that was started, even if it or its setup failed.
+    pub teardown: &'a [TestHook],
+    pub before_all: &'a [RunHook],
+    /// Run at the end of every run, even if a `before_all` hook failed.
+    pub after_all: &'a [RunHook],
+}
+
+impl Hooks<'static> {
+    /// Every hook registered with the hook attributes.
+    #[cfg(feature = "linkme")]
+    pub fn registered() -> Self {
+        Hooks {
+            setup: &super::SETUP,
+            teardown: &super::TEARDOWN,
+            before_all: &super::BEFORE_ALL,
+            after_all: &super::AFTER_ALL,
+        }
+    }
+
+    #[cfg(not(feature = "linkme"))]
+    pub fn registered() -> Self {
+        Hooks::default()
+    }
+}
+
+impl<'a> Hooks<'a> {
+    pub fn is_empty(&self) -> bool {
+        self.setup.is_empty() && self.teardown.is_empty() && self.before_all.is_empty() && self.after_all.is_empty()
+    }
+}
+
+impl fmt::Debug for TestHook {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_tuple("TestHook")
+            .field(&self.name)
+            .finish()
+    }
+}
+
+impl fmt::Debug for RunHook {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_tuple("RunHook")
+            .field(&self.name)
+            .finish()
+    }
+}
+
+impl fmt::Display for HookKind {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(match self {
+            HookKind::Setup => "setup",
+            HookKind::Teardown => "teardown",
+            HookKind::BeforeAll => "before_all",
+            HookKind::AfterAll => "after_all",
+        })
+    }
+}
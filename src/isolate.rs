@@ -0,0 +1,272 @@
+use core::fmt::{self, Write as _};
+use core::time::Duration;
+use std::env;
+use std::io::{self, Read, Write as _};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::OnceLock;
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use super::{HookKind, RunConfig, Test, TestContext, TestError, TestExpected, TestFailure, TestOutcome, Reporter};
+
+/// Set in the environment of a test's own process to the test's module path and name, so it
+/// runs just that test and reports back to its parent.
+const CHILD_ENV: &str = "MINTEST_CHILD";
+
+/// The test this process was started by `run` for, if it was.
+fn target() -> Option<(&'static str, &'static str)> {
+    static TARGET: OnceLock<Option<String>> = OnceLock::new();
+    TARGET.get_or_init(|| env::var(CHILD_ENV).ok())
+        .as_deref()
+        .and_then(|target| target.split_once(' '))
+}
+
+/// Whether this process was started by `run` for a single test.
+pub fn is_child() -> bool {
+    target().is_some()
+}
+
+/// Whether this process was started by `run` for `test` in particular, so it runs here.
+pub fn is_target(test: &Test) -> bool {
+    target() == Some((test.module_path, test.name))
+}
+
+/// Marks the lines a child writes for its parent, among whatever the test printed itself.
+const PREFIX: &str = "mintest-isolated: ";
+
+/// What a test's process reported, or what became of it.
+pub enum Isolated {
+    Passed(Option<TestError>),
+    Failed(TestError),
+    TimedOut(Duration),
+}
+
+impl Isolated {
+    pub fn outcome(&self) -> TestOutcome<'_> {
+        match self {
+            Isolated::Passed(e) => TestOutcome::Passed(e.as_ref()),
+            Isolated::Failed(e) => TestOutcome::Failed(TestFailure::Error(e)),
+            Isolated::TimedOut(timeout) => TestOutcome::TimedOut(*timeout),
+        }
+    }
+
+    fn parse(event: &str) -> Option<Self> {
+        let (kind, rest) = match event.find(' ') {
+            Some(space) => (&event[..space], Some(&event[space + 1..])),
+            None => (event, None),
+        };
+        match (kind, rest) {
+            ("passed", message) => Some(Isolated::Passed(message.map(unescape))),
+            ("failed", Some(message)) => Some(Isolated::Failed(unescape(message))),
+            ("timed-out", Some(nanos)) => nanos.parse().ok().map(|nanos| Isolated::TimedOut(Duration::from_nanos(nanos))),
+            _ => None,
+        }
+    }
+}
+
+/// Keeps each event on its own line.
+struct Escape<W>(W);
+
+impl<W: fmt::Write> fmt::Write for Escape<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            match c {
+                '\\' => self.0.write_str("\\\\")?,
+                '\n' => self.0.write_str("\\n")?,
+                '\r' => self.0.write_str("\\r")?,
+                c => self.0.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn unescape(s: &str) -> TestError {
+    let mut message = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => message.push('\n'),
+                Some('r') => message.push('\r'),
+                Some(c) => message.push(c),
+                None => (),
+            },
+            c => message.push(c),
+        }
+    }
+    TestError::format(format_args!("{}", message))
+}
+
+/// Reports a child's single test to its parent over stdout.
+pub struct ChildReporter;
+
+impl ChildReporter {
+    fn event(&mut self, kind: &str, message: Option<&dyn fmt::Display>) {
+        let mut line = String::from(PREFIX);
+        line.push_str(kind);
+        if let Some(message) = message {
+            line.push(' ');
+            let _ = write!(Escape(&mut line), "{}", message);
+        }
+        line.push('\n');
+
+        // the process may well be about to exit
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        let _ = stdout.write_all(line.as_bytes());
+        let _ = stdout.flush();
+    }
+}
+
+impl Reporter for ChildReporter {
+    fn test_started(&mut self, _context: &TestContext) {
+        self.event("started", None)
+    }
+
+    fn test_finished(&mut self, _context: &TestContext, outcome: &TestOutcome, _duration: Option<Duration>) {
+        match outcome {
+            TestOutcome::Passed(None) => self.event("passed", None),
+            TestOutcome::Passed(Some(e)) => self.event("passed", Some(e)),
+            TestOutcome::Failed(failure) => self.event("failed", Some(failure)),
+            TestOutcome::TimedOut(timeout) => self.event("timed-out", Some(&timeout.as_nanos())),
+        }
+    }
+
+    fn hook_failed(&mut self, kind: HookKind, name: &str, error: &TestError) {
+        eprintln!("{} hook `{}` failed: {}", kind, name, error);
+    }
+}
+
+fn failed(args: fmt::Arguments, output: &str) -> Isolated {
+    Isolated::Failed(match output.trim_end() {
+        "" => TestError::format(args),
+        output => TestError::format(format_args!("{}\n{}", args, output)),
+    })
+}
+
+/// Whether the process ended the way `std::process::abort` ends one.
+fn aborted(status: ExitStatus) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        // SIGABRT
+        status.signal() == Some(6)
+    }
+
+    #[cfg(windows)]
+    {
+        // `abort()` and `__fastfail`, which std aborts through
+        matches!(status.code(), Some(code) if code == 3 || code as u32 == 0xc000_0409)
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    {
+        !status.success() && status.code().is_none()
+    }
+}
+
+/// Reads a child's output as it comes, so it never blocks on a full pipe.
+fn read_all<R: Read + Send + 'static>(pipe: Option<R>) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut output);
+        }
+        output
+    })
+}
+
+/// Waits for a child to exit, or returns `None` once `timeout` has passed.
+fn wait(child: &mut Child, timeout: Option<Duration>) -> io::Result<Option<ExitStatus>> {
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return child.wait().map(Some),
+    };
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status))
+        }
+        match timeout.checked_sub(start.elapsed()) {
+            Some(left) if left > Duration::ZERO => thread::sleep(left.min(Duration::from_millis(10))),
+            _ => return Ok(None),
+        }
+    }
+}
+
+/// Runs a test in a new process of the current executable, through `run_tests_from_args`.
+///
+/// The process is killed if it's still going after the test's timeout.
+pub fn run(context: &TestContext, config: &RunConfig) -> Isolated {
+    let test = context.test;
+    let exe = match env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => return failed(format_args!("couldn't find the test executable: {}", e), ""),
+    };
+    // the filter keeps anything other than `run_tests_from_args` from running everything
+    let mut command = Command::new(exe);
+    command.env(CHILD_ENV, format!("{} {}", test.module_path, test.name))
+        .args(["--include-ignored", "--exact", test.name])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => return failed(format_args!("couldn't start the test process: {}", e), ""),
+    };
+    let (stdout, stderr) = (read_all(child.stdout.take()), read_all(child.stderr.take()));
+
+    let timeout = test.timeout.or(config.timeout);
+    let status = match wait(&mut child, timeout) {
+        Ok(Some(status)) => status,
+        Ok(None) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Isolated::TimedOut(timeout.unwrap_or_default())
+        },
+        Err(e) => {
+            let _ = child.kill();
+            return failed(format_args!("couldn't wait for the test process: {}", e), "")
+        },
+    };
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+
+    let mut started = 0usize;
+    let mut reported = None;
+    let mut captured = String::new();
+    for line in String::from_utf8_lossy(&stdout).lines() {
+        match line.strip_prefix(PREFIX) {
+            Some("started") => started += 1,
+            Some(event) => reported = Isolated::parse(event).or(reported),
+            None => {
+                captured.push_str(line);
+                captured.push('\n');
+            },
+        }
+    }
+    captured.push_str(&String::from_utf8_lossy(&stderr));
+
+    // a failed test exits with a failure of its own
+    let terminated = match test.expected {
+        TestExpected::Abort => aborted(status),
+        TestExpected::Exit(code) => status.code() == Some(code),
+        _ => false,
+    };
+    match (started, reported, status.success()) {
+        (0, ..) => failed(format_args!("the test process never ran the test ({}); isolated tests need `main` to run them through `run_tests_from_args`", status), &captured),
+        (1, Some(Isolated::Passed(..)), false) =>
+            failed(format_args!("test passed, but its process failed ({})", status), &captured),
+        (1, Some(reported), _) => reported,
+        (1, None, _) if terminated => Isolated::Passed(Some(TestError::format(format_args!("test process ended with {}", status)))),
+        (1, None, _) if test.expected.is_termination() =>
+            failed(format_args!("expected {}, but the test process ended with {}", test.expected, status), &captured),
+        (1, None, false) =>
+            failed(format_args!("test process failed ({})", status), &captured),
+        (1, None, true) =>
+            failed(format_args!("test process exited before the test finished ({})", status), &captured),
+        (started, ..) =>
+            failed(format_args!("{} tests in `{}` are named `{}`, so it can't be run on its own", started, test.module_path, test.name), ""),
+    }
+}
@@ -4,13 +4,39 @@
 
 use core::fmt;
 use core::marker::PhantomData;
+use core::panic::Location;
+use core::time::Duration;
 
 #[cfg(feature = "alloc")]
 extern crate alloc;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
 
 pub use mintest_impl::*;
 pub use mintest_impl::test as mintest;
 
+mod report;
+mod message;
+mod hook;
+mod executor;
+mod order;
+pub mod property;
+#[cfg(feature = "std")]
+mod unwind;
+#[cfg(feature = "std")]
+mod timeout;
+#[cfg(feature = "std")]
+mod isolate;
+#[cfg(feature = "std")]
+mod parallel;
+
+pub use self::message::ErrorMessage;
+pub use self::hook::{TestHook, RunHook, HookKind, Hooks};
+pub use self::property::{Arbitrary, Rng};
+pub use self::executor::block_on;
+pub use self::order::TestOrder;
+pub use self::report::{Reporter, OutputFormat, PrettyReporter, TerseReporter, TapReporter, JunitReporter, JsonReporter, TestOutcome, TestFailure};
+
 pub trait Platform {
     type Stderr: fmt::Write;
 
@@ -18,12 +44,63 @@ pub trait Platform {
     fn abort() -> !;
     fn stderr() -> Self::Stderr;
 
+    /// Where machine-readable output goes, apart from anything else written to `stderr`, if the
+    /// platform has somewhere.
+    fn stdout() -> Self::Stderr {
+        Self::stderr()
+    }
+
+    /// A monotonic clock used to time tests, if the platform has one.
+    fn clock() -> Option<Clock> {
+        None
+    }
+
+    /// A watchdog that resets the target when a test runs past its timeout, if the platform has one.
+    fn watchdog() -> Option<Watchdog> {
+        None
+    }
+
     #[cfg(not(feature = "unstable-test"))]
     fn run() -> ! {
         run_tests::<Self, _, _>(TESTS)
     }
 }
 
+#[derive(Debug, Copy, Clone)]
+pub struct Clock {
+    pub ticks: fn() -> u64,
+    /// Ticks per second. A clock with no frequency can't measure anything, so gives no time at all.
+    pub frequency: u64,
+}
+
+impl Clock {
+    #[inline]
+    pub fn now(&self) -> u64 {
+        (self.ticks)()
+    }
+
+    pub fn duration(&self, ticks: u64) -> Duration {
+        let frequency = self.frequency as u128;
+        let nanos = (ticks as u128 * 1_000_000_000).checked_div(frequency).unwrap_or(0);
+        Duration::new((nanos / 1_000_000_000) as u64, (nanos % 1_000_000_000) as u32)
+    }
+
+    #[inline]
+    pub fn elapsed(&self, since: u64) -> Duration {
+        self.duration(self.now().wrapping_sub(since))
+    }
+}
+
+/// Hooks for a hardware or software watchdog, armed around each test that has a timeout.
+#[derive(Debug, Copy, Clone)]
+pub struct Watchdog {
+    /// Starts the watchdog, or reloads it if it's already running, so it fires after `timeout`.
+    pub arm: fn(Duration),
+    /// Called after every test, to keep a watchdog that can't be disarmed from firing between tests.
+    pub feed: fn(),
+    pub disarm: fn(),
+}
+
 #[cfg(feature = "cortex-m-semihosting")]
 mod cortex_m {
     use cortex_m_semihosting::{HStderr, debug, hstderr};
@@ -88,6 +165,8 @@ pub type DefaultPlatform = SemihostingPlatform;
 #[cfg(feature = "std")]
 mod std_platform {
     pub use std::{self, io, process};
+    use std::sync::OnceLock;
+    use std::time::Instant;
 
     pub struct WriteWrapper<W>(W);
 
@@ -97,10 +176,32 @@ mod std_platform {
         }
     }
 
+    /// Either standard stream, so `StdPlatform` can give out both as its `Stderr`.
+    pub enum StdStream {
+        Stdout(io::Stdout),
+        Stderr(io::Stderr),
+    }
+
+    impl io::Write for StdStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            match self {
+                StdStream::Stdout(stdout) => stdout.write(buf),
+                StdStream::Stderr(stderr) => stderr.write(buf),
+            }
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            match self {
+                StdStream::Stdout(stdout) => stdout.flush(),
+                StdStream::Stderr(stderr) => stderr.flush(),
+            }
+        }
+    }
+
     pub struct StdPlatform;
 
     impl super::Platform for StdPlatform {
-        type Stderr = WriteWrapper<io::Stderr>;
+        type Stderr = WriteWrapper<StdStream>;
 
         #[inline]
         fn exit() -> ! {
@@ -113,12 +214,37 @@ mod std_platform {
         }
 
         #[inline]
-        fn stderr() -> WriteWrapper<io::Stderr> {
-            WriteWrapper(io::stderr())
+        fn stderr() -> WriteWrapper<StdStream> {
+            WriteWrapper(StdStream::Stderr(io::stderr()))
+        }
+
+        #[inline]
+        fn stdout() -> WriteWrapper<StdStream> {
+            WriteWrapper(StdStream::Stdout(io::stdout()))
+        }
+
+        fn clock() -> Option<super::Clock> {
+            Some(super::Clock {
+                ticks: || {
+                    static EPOCH: OnceLock<Instant> = OnceLock::new();
+                    EPOCH.get_or_init(Instant::now).elapsed().as_nanos() as u64
+                },
+                frequency: 1_000_000_000,
+            })
+        }
+
+        #[cfg(not(feature = "unstable-test"))]
+        fn run() -> ! {
+            super::run_tests_from_args::<Self, _, _>(super::TESTS)
         }
     }
 }
 
+#[cfg(feature = "std")]
+mod args;
+
+#[cfg(feature = "std")]
+pub use self::args::{Args, run_tests_from_args};
 #[cfg(feature = "std")]
 pub use self::std_platform::StdPlatform;
 #[cfg(feature = "std")]
@@ -200,13 +326,79 @@ impl<T: Into<()>> IntoTestResult for Option<T> {
     }
 }
 
+/// A test argument built before the test runs, and torn down when dropped.
+pub trait Fixture: Sized {
+    fn build(context: &TestContext) -> Result<Self, TestError>;
+}
+
+/// Unwraps a `Result` or `Option` into a `TestError` instead of panicking.
+pub trait TestUnwrap: Sized {
+    type Output;
+
+    #[track_caller]
+    fn test_unwrap(self) -> Result<Self::Output, TestError>;
+
+    #[track_caller]
+    fn test_expect(self, msg: &str) -> Result<Self::Output, TestError>;
+}
+
+impl<T> TestUnwrap for Option<T> {
+    type Output = T;
+
+    #[track_caller]
+    fn test_unwrap(self) -> Result<T, TestError> {
+        let location = Location::caller();
+        self.ok_or_else(|| TestError::format(format_args!("{}: {}", location, TestError::none_error())))
+    }
+
+    #[track_caller]
+    fn test_expect(self, msg: &str) -> Result<T, TestError> {
+        let location = Location::caller();
+        self.ok_or_else(|| TestError::format(format_args!("{}: {}", location, msg)))
+    }
+}
+
+impl<T, E: fmt::Debug> TestUnwrap for Result<T, E> {
+    type Output = T;
+
+    #[track_caller]
+    fn test_unwrap(self) -> Result<T, TestError> {
+        let location = Location::caller();
+        self.map_err(|e| TestError::format(format_args!("{}: called `test_unwrap()` on an `Err` value: {:?}", location, e)))
+    }
+
+    #[track_caller]
+    fn test_expect(self, msg: &str) -> Result<T, TestError> {
+        let location = Location::caller();
+        self.map_err(|e| TestError::format(format_args!("{}: {}: {:?}", location, msg, e)))
+    }
+}
+
 pub enum TestError {
-    Debug(&'static dyn fmt::Debug),
-    Display(&'static dyn fmt::Display),
+    /// Borrowed values must be `Sync`, since a test with a timeout or run under `--test-threads`
+    /// hands its error back from another thread.
+    Debug(&'static (dyn fmt::Debug + Sync)),
+    /// `Sync` for the same reason as `Debug`.
+    Display(&'static (dyn fmt::Display + Sync)),
+    Message(ErrorMessage),
+    /// An error that didn't match the pattern of a `should_fail(matches = ...)` test.
+    Mismatch(ErrorMessage),
+    /// A fixture argument couldn't be built, so the test never ran.
+    Setup {
+        fixture: &'static str,
+        message: ErrorMessage,
+    },
+    /// A panic caught under `std`.
     #[cfg(feature = "std")]
-    Panic(Box<dyn std::any::Any + Send + 'static>),
+    Panic {
+        /// What the test panicked with.
+        payload: Box<dyn std::any::Any + Send + 'static>,
+        /// Where the panic happened, if the run's panic hook saw it.
+        location: Option<String>,
+    },
 }
 
+#[derive(Clone)]
 pub struct TestContext<'a> {
     pub test: &'a Test,
     pub index: usize,
@@ -218,7 +410,35 @@ pub struct TestContext<'a> {
 pub enum TestExpected {
     Success,
     Fail,
+    /// An error whose `Display` text contains the given string.
+    FailWith(&'static str),
+    /// An error matching the given pattern, checked by the test's generated wrapper.
+    FailMatching(&'static str),
     Panic,
+    /// A panic whose message contains the given string.
+    PanicWith(&'static str),
+    /// The test's process aborting, as `std::process::abort` or a `panic = "abort"` panic would.
+    Abort,
+    /// The test's process exiting with the given status.
+    Exit(i32),
+}
+
+impl TestExpected {
+    #[inline]
+    pub fn is_fail(&self) -> bool {
+        matches!(self, TestExpected::Fail | TestExpected::FailWith(..) | TestExpected::FailMatching(..))
+    }
+
+    #[inline]
+    pub fn is_panic(&self) -> bool {
+        matches!(self, TestExpected::Panic | TestExpected::PanicWith(..))
+    }
+
+    /// Whether the test is expected to end its process, so has to run in one of its own.
+    #[inline]
+    pub fn is_termination(&self) -> bool {
+        matches!(self, TestExpected::Abort | TestExpected::Exit(..))
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -232,11 +452,31 @@ pub enum TestStatus {
 pub struct Test {
     pub status: TestStatus,
     pub name: &'static str,
+    /// Where the test was defined, which `TestOrder::Sorted` sorts by first.
+    pub module_path: &'static str,
     pub test: TestFn,
     pub expected: TestExpected,
+    /// How long the test may run for before it's reported as timed out, overriding `RunConfig::timeout`.
+    pub timeout: Option<Duration>,
+    /// Never run alongside other tests, for those that touch global state.
+    pub serial: bool,
+    /// Names of what the test needs to itself; tests claiming the same one never run at once.
+    pub resources: &'static [&'static str],
     //failure_handler: Option<fn(TestError)>, // TODO
 }
 
+impl Test {
+    pub(crate) fn call(&self, context: TestContext) -> TestResult {
+        match self.test {
+            TestFn::Static(f) => f(context),
+            TestFn::Plain(f) => {
+                f();
+                Ok(())
+            },
+        }
+    }
+}
+
 impl AsRef<Test> for Test {
     fn as_ref(&self) -> &Self {
         self
@@ -269,8 +509,12 @@ impl fmt::Debug for Test {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         fmt.debug_struct("Test")
             .field("name", &self.name)
+            .field("module_path", &self.module_path)
             .field("status", &self.status)
             .field("expected", &self.expected)
+            .field("timeout", &self.timeout)
+            .field("serial", &self.serial)
+            .field("resources", &self.resources)
             .field("test", &self.test)
             //.field("failure_handler", &self.failure_handler.as_ref().map(|_| "<FN>"))
             .finish()
@@ -286,15 +530,21 @@ impl fmt::Debug for TestError {
             TestError::Debug(d) => fmt.debug_tuple("TestError")
                 .field(d)
                 .finish(),
+            TestError::Message(m) => fmt.debug_tuple("TestError")
+                .field(m)
+                .finish(),
+            TestError::Mismatch(m) => fmt.debug_tuple("TestError::Mismatch")
+                .field(m)
+                .finish(),
+            TestError::Setup { fixture, message } => fmt.debug_struct("TestError::Setup")
+                .field("fixture", fixture)
+                .field("message", message)
+                .finish(),
             #[cfg(feature = "std")]
-            TestError::Panic(panic) => fmt.debug_tuple("TestError")
-                .field(&if let Some(panic) = panic.downcast_ref::<String>() {
-                    &panic[..]
-                } else if let Some(panic) = panic.downcast_ref::<&'static str>() {
-                    panic
-                } else {
-                    "panic"
-                }).finish(),
+            TestError::Panic { payload, location } => fmt.debug_struct("TestError::Panic")
+                .field("message", &unwind::panic_message(&**payload))
+                .field("location", location)
+                .finish(),
         }
     }
 }
@@ -304,14 +554,13 @@ impl fmt::Display for TestError {
         match self {
             TestError::Display(d) => fmt::Display::fmt(d, fmt),
             TestError::Debug(d) => fmt::Debug::fmt(d, fmt),
+            TestError::Message(m) | TestError::Mismatch(m) | TestError::Setup { message: m, .. } => fmt::Display::fmt(m, fmt),
             #[cfg(feature = "std")]
-            TestError::Panic(panic) => fmt.write_str(if let Some(panic) = panic.downcast_ref::<String>() {
-                &panic[..]
-            } else if let Some(panic) = panic.downcast_ref::<&'static str>() {
-                panic
-            } else {
-                "panic"
-            }),
+            TestError::Panic { payload, location: Some(location) } =>
+                write!(fmt, "panicked at {}: {}", location, unwind::panic_message(&**payload)),
+            #[cfg(feature = "std")]
+            TestError::Panic { payload, location: None } =>
+                fmt.write_str(unwind::panic_message(&**payload)),
         }
     }
 }
@@ -321,12 +570,17 @@ impl fmt::Display for TestExpected {
         fmt.write_str(match self {
             TestExpected::Success => "success",
             TestExpected::Fail => "failure",
+            TestExpected::FailWith(expected) => return write!(fmt, "failure containing {:?}", expected),
+            TestExpected::FailMatching(pattern) => return write!(fmt, "failure matching `{}`", pattern),
             TestExpected::Panic => "panic",
+            TestExpected::PanicWith(expected) => return write!(fmt, "panic containing {:?}", expected),
+            TestExpected::Abort => "abort",
+            TestExpected::Exit(code) => return write!(fmt, "exit with status {}", code),
         })
     }
 }
 
-impl<S: fmt::Display> From<&'static S> for TestError {
+impl<S: fmt::Display + Sync> From<&'static S> for TestError {
     #[inline]
     fn from(s: &'static S) -> Self {
         TestError::Display(s as &_)
@@ -345,24 +599,312 @@ impl TestError {
     pub fn none_error() -> Self {
         TestError::Display(&"None?")
     }
+
+    pub fn format(args: fmt::Arguments) -> Self {
+        TestError::Message(ErrorMessage::format(args))
+    }
+
+    pub fn setup(fixture: &'static str, error: TestError) -> Self {
+        TestError::Setup {
+            fixture,
+            message: ErrorMessage::format(format_args!("{}", error)),
+        }
+    }
 }
 
 pub const OK: TestResult = Ok(());
 pub fn ok() -> TestResult { OK }
-pub fn err<S: fmt::Display>(s: &'static S) -> TestResult { Err(TestError::Display(s as &_)) }
-pub fn err_debug<S: fmt::Debug>(s: &'static S) -> TestResult { Err(TestError::Debug(s as &_)) }
+/// Fails with `s`, which has to be `Sync` like the value in `TestError::Display`.
+pub fn err<S: fmt::Display + Sync>(s: &'static S) -> TestResult { Err(TestError::Display(s as &_)) }
+/// Fails with `s`, which has to be `Sync` like the value in `TestError::Debug`.
+pub fn err_debug<S: fmt::Debug + Sync>(s: &'static S) -> TestResult { Err(TestError::Debug(s as &_)) }
 
 #[cfg(all(feature = "linkme", not(all(feature = "unstable-test", not(feature = "test")))))]
 #[linkme::distributed_slice]
 pub static TESTS: [Test] = [..];
 
-pub fn test_all<I: AsRef<Test>, T: IntoIterator<Item=I>>(fmt: &mut dyn fmt::Write, tests: T) -> MainResult where
-T::IntoIter: Clone {
-    #[cfg(feature = "color-backtrace")]
+#[cfg(feature = "linkme")]
+#[linkme::distributed_slice]
+pub static SETUP: [TestHook] = [..];
+
+#[cfg(feature = "linkme")]
+#[linkme::distributed_slice]
+pub static TEARDOWN: [TestHook] = [..];
+
+#[cfg(feature = "linkme")]
+#[linkme::distributed_slice]
+pub static BEFORE_ALL: [RunHook] = [..];
+
+#[cfg(feature = "linkme")]
+#[linkme::distributed_slice]
+pub static AFTER_ALL: [RunHook] = [..];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum RunIgnored {
+    /// Report skipped tests without running them.
+    #[default]
+    No,
+    /// Run skipped tests alongside everything else.
+    Yes,
+    /// Run only the skipped tests.
+    Only,
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct RunConfig<'a> {
+    /// Only run tests whose names contain one of these (or equal one, if `exact` is set).
+    /// An empty list runs everything.
+    pub filters: &'a [&'a str],
+    /// Never run tests whose names contain one of these (or equal one, if `exact` is set).
+    pub skip: &'a [&'a str],
+    pub exact: bool,
+    pub ignored: RunIgnored,
+    pub format: OutputFormat,
+    /// Times each test when set; `run_tests` fills this in from `Platform::clock`.
+    pub clock: Option<Clock>,
+    pub hooks: Hooks<'a>,
+    /// How long a test may run for when it doesn't set its own timeout.
+    /// Enforced by a worker thread under `std`, and by the `watchdog` if there is one.
+    /// The test's error comes back from that thread, which is why `TestError` only borrows `Sync` values.
+    pub timeout: Option<Duration>,
+    /// Stop the run after the first test that times out, rather than leaving it behind and carrying on.
+    /// Tests after it that `--test-threads` workers had already started are reported as they
+    /// went, and the rest as skipped.
+    pub abort_on_timeout: bool,
+    /// Armed around tests with a timeout; `run_tests` fills this in from `Platform::watchdog`.
+    pub watchdog: Option<Watchdog>,
+    /// Run this many tests at once under `std`, other than `serial` ones and those holding the same
+    /// `resources`. Results are still reported in order, and tests run one at a time when there's
+    /// a `watchdog`.
+    pub test_threads: Option<usize>,
+    /// Run each test in a process of its own, so an abort or `process::exit` only fails that test.
+    /// Only under `std`, where the test binary is re-run through `run_tests_from_args`, which
+    /// runs the test between its setup and teardown hooks. `before_all` and `after_all` hooks run
+    /// once, in this process, so whatever they set up in memory isn't seen by the tests. The
+    /// process is killed if the test times out.
+    pub isolate: bool,
+    /// Sorted by default, and shuffled by `run_tests_from_args` when asked.
+    pub order: TestOrder,
+}
+
+impl<'a> RunConfig<'a> {
+    /// Fills in whatever the platform and the registered hooks provide.
+    pub(crate) fn with_platform<P: Platform + ?Sized>(&self) -> Self {
+        RunConfig {
+            clock: self.clock.or_else(P::clock),
+            watchdog: self.watchdog.or_else(P::watchdog),
+            hooks: match self.hooks.is_empty() {
+                true => Hooks::registered(),
+                false => self.hooks,
+            },
+            ..*self
+        }
+    }
+
+    fn name_matches(&self, pattern: &str, name: &str) -> bool {
+        match self.exact {
+            true => name == pattern,
+            false => name.contains(pattern),
+        }
+    }
+
+    pub fn is_filtered(&self, test: &Test) -> bool {
+        let included = self.filters.is_empty() || self.filters.iter().any(|f| self.name_matches(f, test.name));
+        let skipped = self.skip.iter().any(|s| self.name_matches(s, test.name));
+        let ignored = match (self.ignored, test.status) {
+            (RunIgnored::Only, TestStatus::Skip(..)) => false,
+            (RunIgnored::Only, _) => true,
+            _ => false,
+        };
+        !included || skipped || ignored
+    }
+
+    fn status(&self, test: &Test) -> TestStatus {
+        let status = match (self.ignored, test.status) {
+            (RunIgnored::Yes, TestStatus::Skip(..)) | (RunIgnored::Only, TestStatus::Skip(..)) => TestStatus::Enable,
+            (_, status) => status,
+        };
+        match status {
+            #[cfg(not(feature = "std"))]
+            TestStatus::Enable if test.expected.is_panic() =>
+                TestStatus::Skip(Some("no-std but panic expected")),
+            #[cfg(not(feature = "std"))]
+            TestStatus::Enable if test.expected.is_termination() =>
+                TestStatus::Skip(Some("no-std but process termination expected")),
+            status => status,
+        }
+    }
+}
+
+fn catch<F: FnOnce() -> TestResult>(expected: bool, f: F) -> TestResult {
+    #[cfg(feature = "std")]
+    return unwind::catch(expected, f);
+
+    #[cfg(not(feature = "std"))]
     {
-        use color_backtrace::{install_with_settings, Settings, Verbosity};
-        install_with_settings(Settings::new().verbosity(Verbosity::Medium))
+        let _ = expected;
+        f()
     }
+}
+
+/// Runs hooks in order, returning the first one to fail.
+fn run_hooks<H, F: Fn(&H) -> TestResult>(hooks: &[H], stop_on_failure: bool, run: F) -> Option<(&H, TestError)> {
+    let mut failure = None;
+    for hook in hooks {
+        if let Err(e) = catch(false, || run(hook)) {
+            if failure.is_none() {
+                failure = Some((hook, e));
+            }
+            if stop_on_failure {
+                break
+            }
+        }
+    }
+    failure
+}
+
+/// What became of a test that ran, before it's held up against what was expected of it.
+enum Ran {
+    Here {
+        setup: Option<(&'static str, TestError)>,
+        result: TestResult,
+        /// The timeout the test ran into, if it did.
+        expired: Option<Duration>,
+        teardown: Option<(&'static str, TestError)>,
+    },
+    #[cfg(feature = "std")]
+    Isolated(isolate::Isolated),
+}
+
+impl Ran {
+    fn timed_out(&self) -> bool {
+        match self {
+            Ran::Here { expired, .. } => expired.is_some(),
+            #[cfg(feature = "std")]
+            Ran::Isolated(isolated) => matches!(isolated, isolate::Isolated::TimedOut(..)),
+        }
+    }
+
+    /// The teardown hook that failed after the test, if one did.
+    fn teardown(&self) -> Option<(&'static str, &TestError)> {
+        match self {
+            Ran::Here { teardown, .. } => teardown.as_ref().map(|(name, e)| (*name, e)),
+            #[cfg(feature = "std")]
+            Ran::Isolated(..) => None,
+        }
+    }
+
+    fn outcome(&self, test: &Test) -> TestOutcome<'_> {
+        let (setup, result, expired, teardown) = match self {
+            Ran::Here { setup, result, expired, teardown } => (setup, result, *expired, teardown),
+            #[cfg(feature = "std")]
+            Ran::Isolated(isolated) => return isolated.outcome(),
+        };
+        let outcome = match (result, test.expected) {
+            #[cfg(not(feature = "std"))]
+            (_, TestExpected::Panic) | (_, TestExpected::PanicWith(..)) => panic!("no-std but panic expected"),
+            #[cfg(not(feature = "std"))]
+            (_, TestExpected::Abort) | (_, TestExpected::Exit(..)) => panic!("no-std but process termination expected"),
+            (Err(e @ TestError::Setup { fixture, .. }), _) => TestOutcome::Failed(TestFailure::Hook {
+                kind: HookKind::Setup,
+                name: fixture,
+                error: e,
+            }),
+            (Ok(()), TestExpected::Success) => TestOutcome::Passed(None),
+            (Ok(()), expected) => TestOutcome::Failed(TestFailure::UnexpectedSuccess(expected)),
+            (Err(e), TestExpected::Success) => TestOutcome::Failed(TestFailure::Error(e)),
+            #[cfg(feature = "std")]
+            (Err(panic @ TestError::Panic { .. }), TestExpected::Panic) => TestOutcome::Passed(Some(panic)),
+            #[cfg(feature = "std")]
+            (Err(panic @ TestError::Panic { payload, .. }), TestExpected::PanicWith(expected))
+                if unwind::panic_message(&**payload).contains(expected) => TestOutcome::Passed(Some(panic)),
+            #[cfg(feature = "std")]
+            (Err(e), expected @ TestExpected::Panic) | (Err(e), expected @ TestExpected::PanicWith(..)) => TestOutcome::Failed(TestFailure::UnexpectedError {
+                expected,
+                error: e,
+            }),
+            #[cfg(feature = "std")]
+            (Err(panic @ TestError::Panic { .. }), expected) => TestOutcome::Failed(TestFailure::UnexpectedError {
+                expected,
+                error: panic,
+            }),
+            #[cfg(feature = "std")]
+            (Err(e), expected @ TestExpected::Abort) | (Err(e), expected @ TestExpected::Exit(..)) => TestOutcome::Failed(TestFailure::UnexpectedError {
+                expected,
+                error: e,
+            }),
+            (Err(e @ TestError::Mismatch(..)), expected) => TestOutcome::Failed(TestFailure::UnexpectedError {
+                expected,
+                error: e,
+            }),
+            (Err(e), expected @ TestExpected::FailWith(substring)) if !message::contains(e, substring) =>
+                TestOutcome::Failed(TestFailure::UnexpectedError {
+                    expected,
+                    error: e,
+                }),
+            (Err(e), _) => TestOutcome::Passed(Some(e)),
+        };
+        match (setup, expired, teardown, outcome) {
+            (Some((name, e)), _, _, _) => TestOutcome::Failed(TestFailure::Hook {
+                kind: HookKind::Setup,
+                name,
+                error: e,
+            }),
+            (None, Some(timeout), _, _) => TestOutcome::TimedOut(timeout),
+            (None, None, Some((name, e)), TestOutcome::Passed(..)) => TestOutcome::Failed(TestFailure::Hook {
+                kind: HookKind::Teardown,
+                name,
+                error: e,
+            }),
+            (_, _, _, outcome) => outcome,
+        }
+    }
+}
+
+/// Runs an enabled test between its setup and teardown hooks, timing it if there's a clock.
+fn run_enabled(config: &RunConfig, context: &TestContext) -> (Ran, Option<Duration>) {
+    #[cfg(feature = "std")]
+    {
+        let test = context.test;
+        // a test can't be seen to end its process from inside it
+        if (config.isolate || test.expected.is_termination()) && !isolate::is_target(test) {
+            let start = config.clock.map(|clock| clock.now());
+            let isolated = isolate::run(context, config);
+            let elapsed = config.clock.and_then(|clock| start.map(|start| clock.elapsed(start)));
+            return (Ran::Isolated(isolated), elapsed)
+        }
+
+        if let Some(timeout) = test.timeout.or(config.timeout) {
+            return timeout::run(config, context, timeout)
+        }
+    }
+
+    run_hooked(config, context)
+}
+
+/// Runs a test between its setup and teardown hooks, on the current thread.
+fn run_hooked(config: &RunConfig, context: &TestContext) -> (Ran, Option<Duration>) {
+    let test = context.test;
+    let setup = run_hooks(config.hooks.setup, true, |hook| (hook.hook)(context));
+    let start = config.clock.map(|clock| clock.now());
+    let result = match setup {
+        None => catch(test.expected.is_panic(), || test.call(context.clone())),
+        Some(..) => Ok(()),
+    };
+    let elapsed = config.clock.and_then(|clock| start.map(|start| clock.elapsed(start)));
+    let teardown = run_hooks(config.hooks.teardown, false, |hook| (hook.hook)(context));
+    (Ran::Here {
+        setup: setup.map(|(hook, e)| (hook.name, e)),
+        result,
+        expired: None,
+        teardown: teardown.map(|(hook, e)| (hook.name, e)),
+    }, elapsed)
+}
+
+pub fn test_all<I: AsRef<Test>, T: IntoIterator<Item=I>>(config: &RunConfig, reporter: &mut dyn Reporter, tests: T) -> MainResult where
+T::IntoIter: Clone {
+    #[cfg(feature = "std")]
+    unwind::install_hook();
 
     let test_filter = |t: &I| match t.as_ref().status {
         TestStatus::Disable => false,
@@ -370,90 +912,169 @@ T::IntoIter: Clone {
     };
 
     let tests = tests.into_iter().filter(test_filter);
+    let available = tests.clone().count();
+    let tests = tests.filter(|t| !config.is_filtered(t.as_ref()));
     let total = tests.clone().count();
-    let _ = writeln!(fmt, "running {} tests", total);
+    let filtered_out = available - total;
+    #[cfg(feature = "alloc")]
+    let tests = {
+        let mut tests: Vec<I> = tests.collect();
+        order::sort(&mut tests, config.order);
+        tests.into_iter()
+    };
+    #[cfg(not(feature = "alloc"))]
+    let tests = order::Ordered::new(tests, config.order);
+    if let TestOrder::Shuffled(seed) = config.order {
+        reporter.run_shuffled(seed);
+    }
+    reporter.run_started(total, filtered_out);
+
+    let mut hooks_failed = 0usize;
+    let before_all = run_hooks(config.hooks.before_all, true, |hook| (hook.hook)());
+    if let Some((hook, e)) = &before_all {
+        hooks_failed += 1;
+        reporter.hook_failed(HookKind::BeforeAll, hook.name, e);
+    }
+    let before_all = before_all.is_some();
+    // taken across the whole run, since tests running at once overlap
+    let start = config.clock.map(|clock| clock.now());
+
+    let run = |reporter: &mut dyn Reporter, context: &TestContext| {
+        let timeout = context.test.timeout.or(config.timeout);
+        let watchdog = match (config.watchdog, timeout) {
+            (Some(watchdog), Some(timeout)) => {
+                // a reset won't leave any other trace of which test it was
+                reporter.watchdog_armed(context, timeout);
+                (watchdog.arm)(timeout);
+                Some(watchdog)
+            },
+            _ => None,
+        };
+        let ran = run_enabled(config, context);
+        if let Some(watchdog) = watchdog {
+            (watchdog.disarm)();
+        }
+        if let Some(watchdog) = config.watchdog {
+            (watchdog.feed)();
+        }
+        ran
+    };
 
-    let (mut passed, mut failed, mut skipped) = (0usize, 0usize, 0usize);
+    #[cfg(feature = "std")]
+    let results = match (config.test_threads, config.watchdog, before_all) {
+        // a single watchdog can't be armed around several tests at once
+        (Some(threads), None, false) if threads > 1 => {
+            let tests = tests.as_slice();
+            let jobs = tests.iter().map(AsRef::as_ref).enumerate()
+                .filter(|(_, test)| matches!(config.status(test), TestStatus::Enable))
+                .collect();
+            std::thread::scope(|scope| {
+                let mut pool = parallel::Pool::spawn(scope, threads, config, jobs, total);
+                report_all(config, reporter, tests.iter(), total, before_all, |reporter, context, aborted| match (context.test.serial, aborted) {
+                    // the workers may have got to it already
+                    (_, true) => pool.abort(context.index),
+                    (false, false) => Some(pool.take(context.index)),
+                    (true, false) => {
+                        let ran = run(reporter, context);
+                        pool.serial_finished(context.index);
+                        Some(ran)
+                    },
+                })
+            })
+        },
+        _ => report_all(config, reporter, tests, total, before_all, |reporter, context, aborted| match aborted {
+            false => Some(run(reporter, context)),
+            true => None,
+        }),
+    };
+
+    #[cfg(not(feature = "std"))]
+    let results = report_all(config, reporter, tests, total, before_all, |reporter, context, aborted| match aborted {
+        false => Some(run(reporter, context)),
+        true => None,
+    });
+    let duration = config.clock.and_then(|clock| start.map(|start| clock.elapsed(start)));
+
+    for hook in config.hooks.after_all {
+        if let Err(e) = catch(false, hook.hook) {
+            hooks_failed += 1;
+            reporter.hook_failed(HookKind::AfterAll, hook.name, &e);
+        }
+    }
+
+    let results = MainResult {
+        filtered_out,
+        hooks_failed,
+        duration,
+        ..results
+    };
+    reporter.run_finished(&results);
+    results
+}
+
+/// Reports on each test in order, getting those that are enabled from `run`.
+///
+/// Once the run is aborted, `run` is told so, and gives `None` for a test it never started.
+fn report_all<I: AsRef<Test>, T: Iterator<Item=I>, F: FnMut(&mut dyn Reporter, &TestContext, bool) -> Option<(Ran, Option<Duration>)>>(
+    config: &RunConfig,
+    reporter: &mut dyn Reporter,
+    tests: T,
+    total: usize,
+    before_all_failed: bool,
+    mut run: F,
+) -> MainResult {
+    let (mut passed, mut failed, mut skipped, mut timed_out) = (0usize, 0usize, 0usize, 0usize);
+    // set once a test times out with `abort_on_timeout`, after which the rest are only reported
+    let mut aborted = false;
     for (index, test) in tests.enumerate() {
         let test = test.as_ref();
-        let status = match test.status {
-            #[cfg(not(feature = "std"))]
-            TestStatus::Enable if test.expected == TestExpected::Panic =>
-                TestStatus::Skip(Some("no-std but panic expected")),
-            status => status,
+        let context = TestContext {
+            index,
+            total,
+            test,
+            _phantom: PhantomData,
         };
 
-        match status {
+        match config.status(test) {
             TestStatus::Disable => (),
+            // the failed hook was reported and counted already
+            TestStatus::Enable if before_all_failed => {
+                skipped += 1;
+                reporter.test_skipped(&context, Some("before_all hook failed"));
+            },
             TestStatus::Enable => {
-                let _ = write!(fmt, "{} ... ", test.name);
-                let context = TestContext {
-                    index,
-                    total,
-                    test,
-                    _phantom: PhantomData,
-                };
-                let test_fn = |context| match test.test {
-                    TestFn::Static(f) => f(context),
-                    TestFn::Plain(f) => {
-                        f();
-                        Ok(())
-                    },
-                };
-                let result = match test.expected {
-                    #[cfg(feature = "std")]
-                    TestExpected::Panic => {
-                        use std::panic;
-
-                        let hook = panic::take_hook();
-                        panic::set_hook(Box::new(|_| ()));
-                        let res = match std::panic::catch_unwind(move || (test_fn)(context)) {
-                            Ok(res) => res,
-                            Err(res) => Err(TestError::Panic(res)),
-                        };
-                        panic::set_hook(hook);
-                        res
+                if !aborted {
+                    reporter.test_started(&context);
+                }
+                let (ran, elapsed) = match run(reporter, &context, aborted) {
+                    Some(ran) => ran,
+                    None => {
+                        skipped += 1;
+                        reporter.test_skipped(&context, Some("run aborted after a timeout"));
+                        continue
                     },
-                    _ => (test_fn)(context),
                 };
-                match (result, test.expected) {
-                    #[cfg(not(feature = "std"))]
-                    (_, TestExpected::Panic) => panic!("no-std but panic expected"),
-                    (Ok(()), TestExpected::Success) => {
-                        passed += 1;
-                        let _ = writeln!(fmt, "\x1b[34mOK\x1b[0m");
-                    },
-                    (Ok(()), expected) => {
-                        failed += 1;
-                        let _ = writeln!(fmt, "\x1b[31mFAIL: expected {} but test passed\x1b[0m", expected);
-                    },
-                    (Err(e), TestExpected::Success) => {
-                        failed += 1;
-                        let _ = writeln!(fmt, "\x1b[31mFAIL: {}\x1b[0m", e);
-                    },
-                    #[cfg(feature = "std")]
-                    (Err(panic @ TestError::Panic(..)), TestExpected::Panic) => {
-                        passed += 1;
-                        let _ = writeln!(fmt, "\x1b[34mOK: {}\x1b[0m", panic);
-                    },
-                    #[cfg(feature = "std")]
-                    (Err(e), TestExpected::Panic) => {
-                        failed += 1;
-                        let _ = writeln!(fmt, "\x1b[31mFAIL: expected panic, got {}\x1b[0m", e);
-                    },
-                    (Err(e), TestExpected::Fail) => {
-                        passed += 1;
-                        let _ = writeln!(fmt, "\x1b[34mOK: {}\x1b[0m", e);
-                    },
+                if aborted {
+                    reporter.test_started(&context);
                 }
+                let outcome = ran.outcome(test);
+                match outcome {
+                    TestOutcome::Passed(..) => passed += 1,
+                    TestOutcome::Failed(..) => failed += 1,
+                    TestOutcome::TimedOut(..) => timed_out += 1,
+                }
+                reporter.test_finished(&context, &outcome, elapsed);
+                // the test's own failure is its outcome, so a teardown failing after it goes alongside
+                if let Some((name, e)) = ran.teardown() {
+                    if !matches!(outcome, TestOutcome::Failed(TestFailure::Hook { kind: HookKind::Teardown, .. })) {
+                        reporter.hook_failed(HookKind::Teardown, name, e);
+                    }
+                }
+                aborted |= ran.timed_out() && config.abort_on_timeout;
             },
-            TestStatus::Skip(Some(reason)) => {
-                skipped += 1;
-                let _ = writeln!(fmt, "{} ... \x1b[33mSkipped: {}\x1b[0m", test.name, reason);
-            },
-            TestStatus::Skip(None) => {
+            TestStatus::Skip(reason) => {
                 skipped += 1;
-                let _ = writeln!(fmt, "{} ... \x1b[33mSkipped\x1b[0m", test.name);
+                reporter.test_skipped(&context, reason);
             },
         }
     }
@@ -462,19 +1083,30 @@ T::IntoIter: Clone {
         passed,
         failed,
         skipped,
+        filtered_out: 0,
+        timed_out,
+        hooks_failed: 0,
+        duration: None,
     }
 }
 
 pub struct MainResult {
-    passed: usize,
-    failed: usize,
-    skipped: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub filtered_out: usize,
+    pub timed_out: usize,
+    /// `before_all` and `after_all` hooks that failed. The tests a `before_all` failure kept from
+    /// running are counted as skipped.
+    pub hooks_failed: usize,
+    /// How long the tests took to run, if they were timed.
+    pub duration: Option<Duration>,
 }
 
 impl MainResult {
     #[inline]
     pub fn succeeded(&self) -> bool {
-        self.failed == 0
+        self.failed == 0 && self.timed_out == 0 && self.hooks_failed == 0
     }
 }
 
@@ -485,7 +1117,17 @@ impl fmt::Display for MainResult {
         } else {
             "FAILED"
         };
-        write!(f, "test result: {}. {} passed; {} failed; {} skipped", result_str, self.passed, self.failed, self.skipped)
+        write!(f, "test result: {}. {} passed; {} failed; {} skipped; {} filtered out", result_str, self.passed, self.failed, self.skipped, self.filtered_out)?;
+        if self.timed_out > 0 {
+            write!(f, "; {} timed out", self.timed_out)?;
+        }
+        if self.hooks_failed > 0 {
+            write!(f, "; {} hooks failed", self.hooks_failed)?;
+        }
+        match self.duration {
+            Some(duration) => write!(f, "; finished in {}.{:02}s", duration.as_secs(), duration.subsec_millis() / 10),
+            None => Ok(()),
+        }
     }
 }
 
@@ -509,11 +1151,16 @@ pub fn runner(tests: &[&test_::TestDescAndFn]) -> ! {
             test_::AlignedTestName(..) | test_::DynTestName(..) =>
                 panic!("dynamic test names unsupported"),
         },
+        // already part of the name
+        module_path: "",
         expected: match test.desc.should_panic {
             test_::ShouldPanic::No => TestExpected::Success,
             test_::ShouldPanic::Yes => TestExpected::Panic,
-            test_::ShouldPanic::YesWithMessage(_) => panic!("should_panic expected message unimplemented"),
+            test_::ShouldPanic::YesWithMessage(expected) => TestExpected::PanicWith(expected),
         },
+        timeout: None,
+        serial: false,
+        resources: &[],
         test: match test.testfn {
             test_::StaticTestFn(f) => TestFn::Plain(f),
             _ => panic!("unsupported test fn"),
@@ -521,24 +1168,47 @@ pub fn runner(tests: &[&test_::TestDescAndFn]) -> ! {
     });
 
     #[cfg(feature = "linkme")]
-    run_tests::<DefaultPlatform, _, _>(TESTS.iter().map(Cow::Borrowed).chain(tests.map(Cow::Owned)));
+    run_tests_from_args::<DefaultPlatform, _, _>(TESTS.iter().map(Cow::Borrowed).chain(tests.map(Cow::Owned)));
 
     #[cfg(not(feature = "linkme"))]
-    run_tests::<DefaultPlatform, _, _>(tests);
+    run_tests_from_args::<DefaultPlatform, _, _>(tests);
 }
 
 #[cfg(all(feature = "unstable-test", not(feature = "test")))]
 pub fn runner(tests: &[&Test]) -> ! {
+    #[cfg(feature = "std")]
+    run_tests_from_args::<DefaultPlatform, _, _>(tests);
+
+    #[cfg(not(feature = "std"))]
     run_tests::<DefaultPlatform, _, _>(tests);
 }
 
 pub fn run_tests<P: Platform + ?Sized, I: AsRef<Test>, T: IntoIterator<Item=I>>(tests: T) -> ! where
 T::IntoIter: Clone {
-    use fmt::Write;
+    run_tests_with::<P, _, _>(&RunConfig::default(), tests)
+}
 
-    let mut stderr = P::stderr();
-    let results = test_all(&mut stderr, tests);
-    let _ = writeln!(stderr, "{}", results);
+pub fn run_tests_with<P: Platform + ?Sized, I: AsRef<Test>, T: IntoIterator<Item=I>>(config: &RunConfig, tests: T) -> ! where
+T::IntoIter: Clone {
+    let config = &config.with_platform::<P>();
+    // tools read the machine-readable formats from stdout, as they would `--list`
+    match config.format {
+        OutputFormat::Pretty =>
+            run_tests_reporting::<P, _, _>(config, &mut PrettyReporter::new(P::stderr()), tests),
+        OutputFormat::Terse =>
+            run_tests_reporting::<P, _, _>(config, &mut TerseReporter::new(P::stderr()), tests),
+        OutputFormat::Tap =>
+            run_tests_reporting::<P, _, _>(config, &mut TapReporter::new(P::stdout()), tests),
+        OutputFormat::Junit =>
+            run_tests_reporting::<P, _, _>(config, &mut JunitReporter::new(P::stdout()), tests),
+        OutputFormat::Json =>
+            run_tests_reporting::<P, _, _>(config, &mut JsonReporter::new(P::stdout()), tests),
+    }
+}
+
+pub fn run_tests_reporting<P: Platform + ?Sized, I: AsRef<Test>, T: IntoIterator<Item=I>>(config: &RunConfig, reporter: &mut dyn Reporter, tests: T) -> ! where
+T::IntoIter: Clone {
+    let results = test_all(config, reporter, tests);
     match results.succeeded() {
         true => P::exit(),
         false => P::abort(),
@@ -551,3 +1221,107 @@ macro_rules! err {
         return $crate::err(&$msg).into()
     };
 }
+
+#[macro_export]
+macro_rules! check {
+    ($cond:expr $(,)?) => {
+        if !$cond {
+            return $crate::internal::core::result::Result::Err($crate::internal::core::convert::From::from(
+                $crate::TestError::format($crate::internal::core::format_args!(
+                    "{}:{}: check failed: `{}`",
+                    $crate::internal::core::file!(), $crate::internal::core::line!(), $crate::internal::core::stringify!($cond),
+                ))
+            ))
+        }
+    };
+    ($cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            return $crate::internal::core::result::Result::Err($crate::internal::core::convert::From::from(
+                $crate::TestError::format($crate::internal::core::format_args!(
+                    "{}:{}: check failed: `{}`: {}",
+                    $crate::internal::core::file!(), $crate::internal::core::line!(), $crate::internal::core::stringify!($cond),
+                    $crate::internal::core::format_args!($($arg)+),
+                ))
+            ))
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __check_binop {
+    ($op:tt, $left:expr, $right:expr, $($arg:tt)*) => {
+        match (&$left, &$right) {
+            (left, right) => if !(*left $op *right) {
+                return $crate::internal::core::result::Result::Err($crate::internal::core::convert::From::from(
+                    $crate::TestError::format($crate::internal::core::format_args!(
+                        "{}:{}: check failed: `{} {} {}`{}\n  left: {:?}\n right: {:?}",
+                        $crate::internal::core::file!(), $crate::internal::core::line!(),
+                        $crate::internal::core::stringify!($left), $crate::internal::core::stringify!($op), $crate::internal::core::stringify!($right),
+                        $crate::internal::core::format_args!($($arg)*),
+                        left, right,
+                    ))
+                ))
+            },
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! check_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::__check_binop!(==, $left, $right, "")
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        $crate::__check_binop!(==, $left, $right, ": {}", $crate::internal::core::format_args!($($arg)+))
+    };
+}
+
+#[macro_export]
+macro_rules! check_ne {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::__check_binop!(!=, $left, $right, "")
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        $crate::__check_binop!(!=, $left, $right, ": {}", $crate::internal::core::format_args!($($arg)+))
+    };
+}
+
+#[macro_export]
+macro_rules! check_matches {
+    ($value:expr, $pat:pat $(if $guard:expr)? $(,)?) => {
+        match $value {
+            $pat $(if $guard)? => (),
+            ref value => return $crate::internal::core::result::Result::Err($crate::internal::core::convert::From::from(
+                $crate::TestError::format($crate::internal::core::format_args!(
+                    "{}:{}: check failed: `{}` matches `{}`\n value: {:?}",
+                    $crate::internal::core::file!(), $crate::internal::core::line!(),
+                    $crate::internal::core::stringify!($value), $crate::internal::core::stringify!($pat $(if $guard)?),
+                    value,
+                ))
+            )),
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! try_unwrap {
+    ($value:expr $(,)?) => {
+        match $crate::TestUnwrap::test_expect($value, $crate::internal::core::concat!("`", $crate::internal::core::stringify!($value), "` failed")) {
+            $crate::internal::core::result::Result::Ok(value) => value,
+            $crate::internal::core::result::Result::Err(e) =>
+                return $crate::internal::core::result::Result::Err($crate::internal::core::convert::From::from(e)),
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! try_expect {
+    ($value:expr, $msg:expr $(,)?) => {
+        match $crate::TestUnwrap::test_expect($value, $msg) {
+            $crate::internal::core::result::Result::Ok(value) => value,
+            $crate::internal::core::result::Result::Err(e) =>
+                return $crate::internal::core::result::Result::Err($crate::internal::core::convert::From::from(e)),
+        }
+    };
+}
@@ -0,0 +1,179 @@
+use core::fmt;
+#[cfg(not(feature = "alloc"))]
+use core::str;
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+
+/// A formatted message, so errors can carry runtime values.
+///
+/// Without `alloc` the message is stored inline; anything written past `CAPACITY` bytes is
+/// dropped, along with anything written after it, and the message is marked as truncated. Every
+/// `TestError` holds one, so the capacity is only enough for a `check_eq!` failure with a typical
+/// path and operands, and can be set with `MINTEST_MESSAGE_CAPACITY` when building.
+#[derive(Clone)]
+pub struct ErrorMessage {
+    #[cfg(feature = "alloc")]
+    buf: String,
+    #[cfg(not(feature = "alloc"))]
+    buf: [u8; ErrorMessage::CAPACITY],
+    #[cfg(not(feature = "alloc"))]
+    len: usize,
+    truncated: bool,
+}
+
+impl ErrorMessage {
+    /// Inline capacity in bytes when built without `alloc`.
+    pub const CAPACITY: usize = match option_env!("MINTEST_MESSAGE_CAPACITY") {
+        Some(capacity) => parse_capacity(capacity),
+        None => 128,
+    };
+
+    #[cfg(feature = "alloc")]
+    pub const fn new() -> Self {
+        ErrorMessage {
+            buf: String::new(),
+            truncated: false,
+        }
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    pub const fn new() -> Self {
+        ErrorMessage {
+            buf: [0; ErrorMessage::CAPACITY],
+            len: 0,
+            truncated: false,
+        }
+    }
+
+    pub fn format(args: fmt::Arguments) -> Self {
+        let mut message = Self::new();
+        let _ = fmt::Write::write_fmt(&mut message, args);
+        message
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn as_str(&self) -> &str {
+        &self.buf
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    pub fn as_str(&self) -> &str {
+        // only ever filled from whole chars by write_str
+        unsafe { str::from_utf8_unchecked(&self.buf[..self.len]) }
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+const fn parse_capacity(capacity: &str) -> usize {
+    let digits = capacity.as_bytes();
+    assert!(!digits.is_empty(), "MINTEST_MESSAGE_CAPACITY must be a number of bytes");
+    let (mut i, mut bytes) = (0, 0usize);
+    while i < digits.len() {
+        assert!(digits[i].is_ascii_digit(), "MINTEST_MESSAGE_CAPACITY must be a number of bytes");
+        bytes = bytes * 10 + (digits[i] - b'0') as usize;
+        i += 1;
+    }
+    bytes
+}
+
+impl Default for ErrorMessage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Write for ErrorMessage {
+    #[cfg(feature = "alloc")]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.buf.push_str(s);
+        Ok(())
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        // anything more would follow a gap where the cut-off text was
+        if self.truncated {
+            return Ok(())
+        }
+        let space = Self::CAPACITY - self.len;
+        let s = if s.len() > space {
+            self.truncated = true;
+            let mut end = space;
+            while !s.is_char_boundary(end) {
+                end -= 1;
+            }
+            &s[..end]
+        } else {
+            s
+        };
+        self.buf[self.len..self.len + s.len()].copy_from_slice(s.as_bytes());
+        self.len += s.len();
+        Ok(())
+    }
+}
+
+impl fmt::Display for ErrorMessage {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(self.as_str())?;
+        match self.truncated {
+            true => fmt.write_str("..."),
+            false => Ok(()),
+        }
+    }
+}
+
+impl fmt::Debug for ErrorMessage {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), fmt)?;
+        match self.truncated {
+            true => fmt.write_str("..."),
+            false => Ok(()),
+        }
+    }
+}
+
+/// Searches formatted output for a substring as it's written, without buffering it.
+struct Contains<'a> {
+    needle: &'a [u8],
+    matched: usize,
+    found: bool,
+}
+
+impl<'a> fmt::Write for Contains<'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for &b in s.as_bytes() {
+            if self.found {
+                break
+            }
+            // the text matched so far is needle[..matched], so fall back to the
+            // longest prefix of the needle that it ends with
+            while self.matched > 0 && self.needle[self.matched] != b {
+                self.matched = (1..self.matched).rev()
+                    .find(|&len| self.needle[..len] == self.needle[self.matched - len..self.matched])
+                    .unwrap_or(0);
+            }
+            if self.needle[self.matched] == b {
+                self.matched += 1;
+            }
+            self.found = self.matched == self.needle.len();
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn contains(value: &dyn fmt::Display, needle: &str) -> bool {
+    if needle.is_empty() {
+        return true
+    }
+
+    let mut contains = Contains {
+        needle: needle.as_bytes(),
+        matched: 0,
+        found: false,
+    };
+    let _ = fmt::Write::write_fmt(&mut contains, format_args!("{}", value));
+    contains.found
+}
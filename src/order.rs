@@ -0,0 +1,94 @@
+use super::{Test, property};
+use super::property::Rng;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum TestOrder {
+    /// The order the tests were given in, which for `TESTS` is up to the linker.
+    Given,
+    /// By module path, then name.
+    #[default]
+    Sorted,
+    /// A permutation that reproduces exactly from the seed, whatever order the tests were given in.
+    Shuffled(u64),
+}
+
+/// Where a test falls in the order, before its position breaks any ties.
+type Key = (u64, &'static str, &'static str);
+
+fn key(seed: Option<u64>, test: &Test) -> Key {
+    // keyed on the test itself rather than its position, so linking differently can't change it
+    let shuffled = seed.map_or(0, |seed| {
+        let mut rng = Rng::new(seed ^ property::seed(test.module_path));
+        Rng::new(rng.next_u64() ^ property::seed(test.name)).next_u64()
+    });
+    (shuffled, test.module_path, test.name)
+}
+
+/// Puts tests in a `TestOrder`.
+#[cfg(feature = "alloc")]
+pub fn sort<I: AsRef<Test>>(tests: &mut [I], order: TestOrder) {
+    let seed = match order {
+        TestOrder::Given => return,
+        TestOrder::Sorted => None,
+        TestOrder::Shuffled(seed) => Some(seed),
+    };
+    // stable, so ties stay in the order given
+    tests.sort_by_key(|test| key(seed, test.as_ref()));
+}
+
+/// Yields tests in a `TestOrder`, without needing anywhere to sort them into.
+///
+/// Each test is found by a pass over the rest, so this takes quadratic time unless the order is `Given`.
+#[cfg(not(feature = "alloc"))]
+#[derive(Clone)]
+pub struct Ordered<I> {
+    tests: I,
+    order: TestOrder,
+    /// The test last yielded, which the next has to come after.
+    last: Option<(Key, usize)>,
+}
+
+#[cfg(not(feature = "alloc"))]
+impl<I> Ordered<I> {
+    pub fn new(tests: I, order: TestOrder) -> Self {
+        Ordered {
+            tests,
+            order,
+            last: None,
+        }
+    }
+}
+
+#[cfg(not(feature = "alloc"))]
+impl<I: Iterator + Clone> Iterator for Ordered<I> where
+I::Item: AsRef<Test> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let seed = match self.order {
+            TestOrder::Given => return self.tests.next(),
+            TestOrder::Sorted => None,
+            TestOrder::Shuffled(seed) => Some(seed),
+        };
+
+        let mut next: Option<((Key, usize), I::Item)> = None;
+        for (position, test) in self.tests.clone().enumerate() {
+            let key = (key(seed, test.as_ref()), position);
+            let after_last = match self.last {
+                Some(last) => key > last,
+                None => true,
+            };
+            let before_next = match &next {
+                Some((next, _)) => key < *next,
+                None => true,
+            };
+            if after_last && before_next {
+                next = Some((key, test));
+            }
+        }
+        next.map(|(key, test)| {
+            self.last = Some(key);
+            test
+        })
+    }
+}
@@ -0,0 +1,169 @@
+use core::marker::PhantomData;
+use core::time::Duration;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::Scope;
+
+use super::{Ran, RunConfig, Test, TestContext, run_enabled};
+
+/// A test that a worker finished, by its index.
+struct Finished(usize, Ran, Option<Duration>);
+
+/// What the workers share.
+struct Queue<'env> {
+    /// Tests no worker has started yet, in order.
+    tests: Vec<(usize, &'env Test)>,
+    /// Resources claimed by the tests being run, once per test.
+    held: Vec<&'static str>,
+    /// Tests from this index on wait for the `serial` test there to finish.
+    until: usize,
+    /// Set once no more tests are to be started.
+    stop: bool,
+}
+
+struct Shared<'env> {
+    queue: Mutex<Queue<'env>>,
+    /// Signalled whenever resources are released, a `serial` test finishes, or the pool is dropped.
+    released: Condvar,
+}
+
+impl<'env> Shared<'env> {
+    fn lock(&self) -> MutexGuard<'_, Queue<'env>> {
+        self.queue.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Takes the first test that's free to start, waiting for one if need be.
+    fn next(&self) -> Option<(usize, &'env Test)> {
+        let mut queue = self.lock();
+        loop {
+            if queue.stop {
+                return None
+            }
+            let (held, until) = (&queue.held, queue.until);
+            let free = queue.tests.iter()
+                .take_while(|(index, _)| *index < until)
+                .position(|(_, test)| test.resources.iter().all(|r| !held.contains(r)));
+            match free {
+                Some(i) => {
+                    let (index, test) = queue.tests.remove(i);
+                    queue.held.extend_from_slice(test.resources);
+                    return Some((index, test))
+                },
+                None if queue.tests.is_empty() => return None,
+                None => queue = self.released.wait(queue).unwrap_or_else(PoisonError::into_inner),
+            }
+        }
+    }
+
+    fn release(&self, test: &Test) {
+        let mut queue = self.lock();
+        for resource in test.resources {
+            if let Some(i) = queue.held.iter().position(|held| held == resource) {
+                queue.held.swap_remove(i);
+            }
+        }
+        self.released.notify_all();
+    }
+}
+
+/// Worker threads running tests ahead of `test_all` reporting them.
+pub struct Pool<'env> {
+    receiver: Receiver<Finished>,
+    finished: Vec<Option<(Ran, Option<Duration>)>>,
+    /// Indices of the `serial` tests, which are left for `test_all` to run.
+    serial: Vec<usize>,
+    shared: Arc<Shared<'env>>,
+}
+
+impl<'env> Pool<'env> {
+    /// Starts `threads` workers on `tests`, each paired with its index among `total`.
+    ///
+    /// The workers run the tests up to the first `serial` one, and only start on those after it
+    /// once it's `serial_finished`.
+    pub fn spawn<'scope>(scope: &'scope Scope<'scope, 'env>, threads: usize, config: &'env RunConfig, tests: Vec<(usize, &'env Test)>, total: usize) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let serial: Vec<usize> = tests.iter().filter(|(_, test)| test.serial).map(|(index, _)| *index).collect();
+        let tests: Vec<_> = tests.into_iter().filter(|(_, test)| !test.serial).collect();
+        let workers = threads.min(tests.len());
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(Queue {
+                tests,
+                held: Vec::new(),
+                until: serial.first().copied().unwrap_or(total),
+                stop: false,
+            }),
+            released: Condvar::new(),
+        });
+        for _ in 0..workers {
+            let (sender, shared) = (sender.clone(), shared.clone());
+            scope.spawn(move || {
+                while let Some((index, test)) = shared.next() {
+                    let context = TestContext {
+                        test,
+                        index,
+                        total,
+                        _phantom: PhantomData,
+                    };
+                    let (ran, elapsed) = run_enabled(config, &context);
+                    shared.release(test);
+                    if sender.send(Finished(index, ran, elapsed)).is_err() {
+                        break
+                    }
+                }
+            });
+        }
+
+        Pool {
+            receiver,
+            finished: (0..total).map(|_| None).collect(),
+            serial,
+            shared,
+        }
+    }
+
+    /// Waits for the test at `index` to finish.
+    pub fn take(&mut self, index: usize) -> (Ran, Option<Duration>) {
+        loop {
+            if let Some(finished) = self.finished[index].take() {
+                return finished
+            }
+            // a test that was started is still seen through after the pool is stopped
+            let Finished(index, ran, elapsed) = self.receiver.recv().expect("test worker exited early");
+            self.finished[index] = Some((ran, elapsed));
+        }
+    }
+
+    /// Stops the workers starting any more tests, then waits for the test at `index` if it was
+    /// already under way.
+    ///
+    /// Returns `None` for a test that never started, which now never will.
+    pub fn abort(&mut self, index: usize) -> Option<(Ran, Option<Duration>)> {
+        let started = {
+            let mut queue = self.shared.lock();
+            queue.stop = true;
+            !queue.tests.iter().any(|(queued, _)| *queued == index)
+        };
+        self.shared.released.notify_all();
+        match started && !self.serial.contains(&index) {
+            true => Some(self.take(index)),
+            false => None,
+        }
+    }
+
+    /// Lets the workers start on the tests after the `serial` test at `index`, up to the next.
+    ///
+    /// Every test before it has been taken by then, so nothing else was running alongside it.
+    pub fn serial_finished(&mut self, index: usize) {
+        let until = self.serial.iter().copied().find(|&serial| serial > index).unwrap_or(self.finished.len());
+        self.shared.lock().until = until;
+        self.shared.released.notify_all();
+    }
+}
+
+impl Drop for Pool<'_> {
+    fn drop(&mut self) {
+        // leave any remaining tests be if the run was cut short
+        self.shared.lock().stop = true;
+        self.shared.released.notify_all();
+    }
+}
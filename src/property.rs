@@ -0,0 +1,245 @@
+use core::fmt;
+
+use super::{TestContext, TestError, TestResult};
+
+/// A small deterministic PRNG (SplitMix64), so failures reproduce from their seed alone.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub const fn new(seed: u64) -> Self {
+        Rng {
+            state: seed,
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_u128(&mut self) -> u128 {
+        (self.next_u64() as u128) << 64 | self.next_u64() as u128
+    }
+
+    /// A value in `0..bound`, or 0 if `bound` is 0.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        match bound {
+            0 => 0,
+            bound => self.next_u64() % bound,
+        }
+    }
+
+    /// `true` roughly once every `n` calls.
+    pub fn one_in(&mut self, n: u64) -> bool {
+        self.below(n) == 0
+    }
+}
+
+/// A property test input that can be generated at random and shrunk towards a simpler value.
+pub trait Arbitrary: Clone + fmt::Debug {
+    fn arbitrary(rng: &mut Rng) -> Self;
+
+    /// The `n`th simpler candidate for this value, or `None` once there are no more.
+    fn shrink(&self, n: usize) -> Option<Self> {
+        let _ = n;
+        None
+    }
+}
+
+/// The seed a property test uses unless it was given one: a hash of its name.
+pub fn seed(name: &str) -> u64 {
+    // FNV-1a
+    name.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, b| (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// Most shrink candidates tried before settling on the current counterexample.
+const MAX_SHRINKS: usize = 1024;
+
+/// Runs `property` against `cases` generated inputs, shrinking the first one it fails for.
+pub fn check<T: Arbitrary, F: FnMut(T) -> TestResult>(context: &TestContext, cases: usize, seed: Option<u64>, mut property: F) -> TestResult {
+    let seed = seed.unwrap_or_else(|| self::seed(context.test.name));
+    let expected_panic = context.test.expected.is_panic();
+    let mut rng = Rng::new(seed);
+    for case in 0..cases {
+        let input = T::arbitrary(&mut rng);
+        let error = match super::catch(expected_panic, || property(input.clone())) {
+            Ok(()) => continue,
+            Err(error) => error,
+        };
+
+        let (mut input, mut error, mut shrinks, mut tries) = (input, error, 0usize, 0usize);
+        let mut n = 0;
+        while tries < MAX_SHRINKS {
+            let candidate = match input.shrink(n) {
+                Some(candidate) => candidate,
+                None => break,
+            };
+            tries += 1;
+            match super::catch(expected_panic, || property(candidate.clone())) {
+                Ok(()) => n += 1,
+                Err(e) => {
+                    input = candidate;
+                    error = e;
+                    shrinks += 1;
+                    n = 0;
+                },
+            }
+        }
+
+        // a panic is what a `should_panic` property is after, so it's left for the test to match
+        #[cfg(feature = "std")]
+        {
+            if let (true, TestError::Panic { .. }) = (expected_panic, &error) {
+                return Err(error)
+            }
+        }
+
+        // the counterexample first, since without `alloc` the message may not hold all of it
+        return Err(TestError::format(format_args!(
+            "input: {:?} (seed {:#x})\n error: {}\n failed after {} cases, shrunk {} times",
+            input, seed, error, case + 1, shrinks,
+        )))
+    }
+    Ok(())
+}
+
+/// Counts `n` down past each of `value`'s shrink candidates, returning the one it lands on.
+fn nth_shrink<T: Arbitrary>(value: &T, n: &mut usize) -> Option<T> {
+    let mut i = 0;
+    while let Some(candidate) = value.shrink(i) {
+        if *n == 0 {
+            return Some(candidate)
+        }
+        *n -= 1;
+        i += 1;
+    }
+    None
+}
+
+macro_rules! arbitrary_int {
+    ($($ty:ty),*) => {
+        $(
+            impl Arbitrary for $ty {
+                fn arbitrary(rng: &mut Rng) -> Self {
+                    // edge cases turn up far more often than they would uniformly
+                    match rng.one_in(8) {
+                        true => [0, 1, <$ty>::MIN, <$ty>::MAX, <$ty>::MIN.wrapping_add(1), <$ty>::MAX.wrapping_sub(1)][rng.below(6) as usize],
+                        false if <$ty>::BITS > 64 => rng.next_u128() as $ty,
+                        false => rng.next_u64() as $ty,
+                    }
+                }
+
+                #[allow(unused_comparisons)]
+                fn shrink(&self, n: usize) -> Option<Self> {
+                    let value = *self;
+                    let towards_zero = match value {
+                        0 => 0,
+                        value if value < 0 => value + 1,
+                        value => value - 1,
+                    };
+                    [0, value / 2, towards_zero].iter().copied().filter(|&candidate| candidate != value).nth(n)
+                }
+            }
+        )*
+    };
+}
+
+arbitrary_int!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+impl Arbitrary for bool {
+    fn arbitrary(rng: &mut Rng) -> Self {
+        rng.next_u64() & 1 != 0
+    }
+
+    fn shrink(&self, n: usize) -> Option<Self> {
+        match (self, n) {
+            (true, 0) => Some(false),
+            _ => None,
+        }
+    }
+}
+
+impl Arbitrary for char {
+    fn arbitrary(rng: &mut Rng) -> Self {
+        match rng.one_in(4) {
+            true => core::char::from_u32(rng.below(0x11_0000) as u32).unwrap_or(char::REPLACEMENT_CHARACTER),
+            false => (b' ' + rng.below(95) as u8) as char,
+        }
+    }
+
+    fn shrink(&self, n: usize) -> Option<Self> {
+        match (self, n) {
+            ('a', _) => None,
+            (_, 0) => Some('a'),
+            _ => None,
+        }
+    }
+}
+
+impl<T: Arbitrary> Arbitrary for Option<T> {
+    fn arbitrary(rng: &mut Rng) -> Self {
+        match rng.one_in(4) {
+            true => None,
+            false => Some(T::arbitrary(rng)),
+        }
+    }
+
+    fn shrink(&self, n: usize) -> Option<Self> {
+        match (self, n) {
+            (None, _) => None,
+            (Some(..), 0) => Some(None),
+            (Some(value), n) => value.shrink(n - 1).map(Some),
+        }
+    }
+}
+
+impl<T: Arbitrary, const N: usize> Arbitrary for [T; N] {
+    fn arbitrary(rng: &mut Rng) -> Self {
+        core::array::from_fn(|_| T::arbitrary(rng))
+    }
+
+    fn shrink(&self, mut n: usize) -> Option<Self> {
+        for (i, value) in self.iter().enumerate() {
+            if let Some(candidate) = nth_shrink(value, &mut n) {
+                let mut shrunk = self.clone();
+                shrunk[i] = candidate;
+                return Some(shrunk)
+            }
+        }
+        None
+    }
+}
+
+macro_rules! arbitrary_tuple {
+    ($($T:ident $i:tt),+) => {
+        impl<$($T: Arbitrary),+> Arbitrary for ($($T,)+) {
+            fn arbitrary(rng: &mut Rng) -> Self {
+                ($($T::arbitrary(rng),)+)
+            }
+
+            fn shrink(&self, mut n: usize) -> Option<Self> {
+                $(
+                    if let Some(candidate) = nth_shrink(&self.$i, &mut n) {
+                        let mut shrunk = self.clone();
+                        shrunk.$i = candidate;
+                        return Some(shrunk)
+                    }
+                )+
+                None
+            }
+        }
+    };
+}
+
+arbitrary_tuple!(A 0);
+arbitrary_tuple!(A 0, B 1);
+arbitrary_tuple!(A 0, B 1, C 2);
+arbitrary_tuple!(A 0, B 1, C 2, D 3);
+arbitrary_tuple!(A 0, B 1, C 2, D 3, E 4);
+arbitrary_tuple!(A 0, B 1, C 2, D 3, E 4, F 5);
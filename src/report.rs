@@ -0,0 +1,176 @@
+use core::fmt;
+use core::time::Duration;
+
+use super::{HookKind, MainResult, TestContext, TestError, TestExpected};
+
+mod terse;
+mod tap;
+mod junit;
+mod json;
+
+pub use self::terse::TerseReporter;
+pub use self::tap::TapReporter;
+pub use self::junit::JunitReporter;
+pub use self::json::JsonReporter;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Pretty,
+    /// A character per test, like libtest's `--format terse`.
+    Terse,
+    /// Test Anything Protocol, version 14.
+    Tap,
+    Junit,
+    /// libtest-compatible JSON events.
+    Json,
+}
+
+pub enum TestOutcome<'a> {
+    /// The test behaved as expected, possibly by returning the error it was expected to.
+    Passed(Option<&'a TestError>),
+    Failed(TestFailure<'a>),
+    /// The test was still running when its timeout ran out.
+    TimedOut(Duration),
+}
+
+pub enum TestFailure<'a> {
+    Error(&'a TestError),
+    UnexpectedSuccess(TestExpected),
+    UnexpectedError {
+        expected: TestExpected,
+        error: &'a TestError,
+    },
+    /// A hook or fixture failed, so the test didn't run or couldn't be cleaned up after.
+    Hook {
+        kind: HookKind,
+        name: &'static str,
+        error: &'a TestError,
+    },
+}
+
+/// Receives events from `test_all` as the run progresses.
+pub trait Reporter {
+    /// The tests are about to run in an order shuffled by `seed`; called before `run_started`.
+    fn run_shuffled(&mut self, seed: u64) {
+        let _ = seed;
+    }
+
+    fn run_started(&mut self, total: usize, filtered_out: usize) {
+        let _ = (total, filtered_out);
+    }
+
+    fn test_started(&mut self, context: &TestContext) {
+        let _ = context;
+    }
+
+    /// The test is about to run with a watchdog armed for `timeout`.
+    ///
+    /// Output written here is all that's left to attribute a reset to the test.
+    fn watchdog_armed(&mut self, context: &TestContext, timeout: Duration) {
+        let _ = (context, timeout);
+    }
+
+    /// `duration` is only known when the run has a `Clock`.
+    fn test_finished(&mut self, context: &TestContext, outcome: &TestOutcome, duration: Option<Duration>) {
+        let _ = (context, outcome, duration);
+    }
+
+    fn test_skipped(&mut self, context: &TestContext, reason: Option<&str>) {
+        let _ = (context, reason);
+    }
+
+    /// A `before_all` or `after_all` hook failed, or a teardown hook failed after a test that
+    /// had already failed some other way.
+    fn hook_failed(&mut self, kind: HookKind, name: &str, error: &TestError) {
+        let _ = (kind, name, error);
+    }
+
+    fn run_finished(&mut self, results: &MainResult) {
+        let _ = results;
+    }
+}
+
+impl<'a> fmt::Display for TestFailure<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TestFailure::Error(e) => fmt::Display::fmt(e, fmt),
+            TestFailure::UnexpectedSuccess(expected) => write!(fmt, "expected {} but test passed", expected),
+            TestFailure::UnexpectedError { expected, error } => write!(fmt, "expected {}, got {}", expected, error),
+            TestFailure::Hook { kind, name, error } => write!(fmt, "{} `{}` failed: {}", kind, name, error),
+        }
+    }
+}
+
+/// Formats a duration as seconds to the millisecond, without pulling in float formatting.
+pub(crate) struct Seconds(pub Duration);
+
+impl fmt::Display for Seconds {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}.{:03}", self.0.as_secs(), self.0.subsec_millis())
+    }
+}
+
+/// Human-readable, colored output.
+pub struct PrettyReporter<W> {
+    fmt: W,
+    shuffle_seed: Option<u64>,
+}
+
+impl<W: fmt::Write> PrettyReporter<W> {
+    pub fn new(fmt: W) -> Self {
+        PrettyReporter {
+            fmt,
+            shuffle_seed: None,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.fmt
+    }
+}
+
+impl<W: fmt::Write> Reporter for PrettyReporter<W> {
+    fn run_shuffled(&mut self, seed: u64) {
+        self.shuffle_seed = Some(seed);
+    }
+
+    fn run_started(&mut self, total: usize, _filtered_out: usize) {
+        let _ = match self.shuffle_seed {
+            Some(seed) => writeln!(self.fmt, "running {} tests (shuffled with seed {})", total, seed),
+            None => writeln!(self.fmt, "running {} tests", total),
+        };
+    }
+
+    fn test_started(&mut self, context: &TestContext) {
+        let _ = write!(self.fmt, "{} ... ", context.test.name);
+    }
+
+    fn test_finished(&mut self, _context: &TestContext, outcome: &TestOutcome, duration: Option<Duration>) {
+        let _ = match outcome {
+            TestOutcome::Passed(None) => write!(self.fmt, "\x1b[34mOK\x1b[0m"),
+            TestOutcome::Passed(Some(e)) => write!(self.fmt, "\x1b[34mOK: {}\x1b[0m", e),
+            TestOutcome::Failed(failure) => write!(self.fmt, "\x1b[31mFAIL: {}\x1b[0m", failure),
+            TestOutcome::TimedOut(timeout) => write!(self.fmt, "\x1b[31mTIMEOUT: still running after {}s\x1b[0m", Seconds(*timeout)),
+        };
+        let _ = match duration {
+            Some(duration) => writeln!(self.fmt, " <{}s>", Seconds(duration)),
+            None => writeln!(self.fmt),
+        };
+    }
+
+    fn test_skipped(&mut self, context: &TestContext, reason: Option<&str>) {
+        let _ = match reason {
+            Some(reason) => writeln!(self.fmt, "{} ... \x1b[33mSkipped: {}\x1b[0m", context.test.name, reason),
+            None => writeln!(self.fmt, "{} ... \x1b[33mSkipped\x1b[0m", context.test.name),
+        };
+    }
+
+    fn hook_failed(&mut self, kind: HookKind, name: &str, error: &TestError) {
+        let _ = writeln!(self.fmt, "\x1b[31m{} hook `{}` failed: {}\x1b[0m", kind, name, error);
+    }
+
+    fn run_finished(&mut self, results: &MainResult) {
+        let _ = writeln!(self.fmt, "{}", results);
+    }
+}
@@ -0,0 +1,113 @@
+use core::fmt::{self, Write};
+use core::time::Duration;
+
+use super::{Reporter, Seconds, TestOutcome};
+use crate::{HookKind, MainResult, TestContext, TestError};
+
+/// Escapes text for use inside a JSON string literal.
+struct JsonEscape<W>(W);
+
+impl<W: Write> Write for JsonEscape<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            match c {
+                '"' => self.0.write_str("\\\"")?,
+                '\\' => self.0.write_str("\\\\")?,
+                '\n' => self.0.write_str("\\n")?,
+                '\r' => self.0.write_str("\\r")?,
+                '\t' => self.0.write_str("\\t")?,
+                c if c.is_control() => write!(self.0, "\\u{:04x}", c as u32)?,
+                c => self.0.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Json<T>(T);
+
+impl<T: fmt::Display> fmt::Display for Json<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_char('"')?;
+        write!(JsonEscape(&mut *fmt), "{}", self.0)?;
+        fmt.write_char('"')
+    }
+}
+
+/// Newline-delimited JSON events, following the schema of libtest's `--format json`.
+///
+/// Failure messages are reported in the `stdout` field, where libtest places captured panic output.
+pub struct JsonReporter<W> {
+    fmt: W,
+    shuffle_seed: Option<u64>,
+}
+
+impl<W: Write> JsonReporter<W> {
+    pub fn new(fmt: W) -> Self {
+        JsonReporter {
+            fmt,
+            shuffle_seed: None,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.fmt
+    }
+}
+
+impl<W: Write> Reporter for JsonReporter<W> {
+    fn run_shuffled(&mut self, seed: u64) {
+        self.shuffle_seed = Some(seed);
+    }
+
+    fn run_started(&mut self, total: usize, _filtered_out: usize) {
+        let _ = match self.shuffle_seed {
+            Some(seed) => writeln!(self.fmt, r#"{{"type":"suite","event":"started","test_count":{},"shuffle_seed":{}}}"#, total, seed),
+            None => writeln!(self.fmt, r#"{{"type":"suite","event":"started","test_count":{}}}"#, total),
+        };
+    }
+
+    fn test_started(&mut self, context: &TestContext) {
+        let _ = writeln!(self.fmt, r#"{{"type":"test","event":"started","name":{}}}"#, Json(context.test.name));
+    }
+
+    fn test_finished(&mut self, context: &TestContext, outcome: &TestOutcome, duration: Option<Duration>) {
+        let name = Json(context.test.name);
+        let _ = match outcome {
+            TestOutcome::Passed(..) =>
+                write!(self.fmt, r#"{{"type":"test","name":{},"event":"ok""#, name),
+            TestOutcome::Failed(failure) =>
+                write!(self.fmt, r#"{{"type":"test","name":{},"event":"failed","stdout":{}"#, name, Json(format_args!("{}\n", failure))),
+            TestOutcome::TimedOut(..) =>
+                write!(self.fmt, r#"{{"type":"test","name":{},"event":"failed","reason":"time limit exceeded""#, name),
+        };
+        let _ = match duration {
+            Some(duration) => writeln!(self.fmt, r#","exec_time":{}}}"#, Seconds(duration)),
+            None => writeln!(self.fmt, "}}"),
+        };
+    }
+
+    fn test_skipped(&mut self, context: &TestContext, reason: Option<&str>) {
+        let name = Json(context.test.name);
+        let _ = match reason {
+            Some(reason) => writeln!(self.fmt, r#"{{"type":"test","name":{},"event":"ignored","message":{}}}"#, name, Json(reason)),
+            None => writeln!(self.fmt, r#"{{"type":"test","name":{},"event":"ignored"}}"#, name),
+        };
+    }
+
+    fn hook_failed(&mut self, kind: HookKind, name: &str, error: &TestError) {
+        let _ = writeln!(self.fmt, r#"{{"type":"hook","kind":"{}","name":{},"event":"failed","stdout":{}}}"#, kind, Json(name), Json(format_args!("{}\n", error)));
+    }
+
+    fn run_finished(&mut self, results: &MainResult) {
+        let _ = write!(self.fmt,
+            r#"{{"type":"suite","event":"{}","passed":{},"failed":{},"ignored":{},"measured":0,"filtered_out":{}"#,
+            if results.succeeded() { "ok" } else { "failed" },
+            results.passed, results.failed + results.timed_out, results.skipped, results.filtered_out,
+        );
+        let _ = match results.duration {
+            Some(duration) => writeln!(self.fmt, r#","exec_time":{}}}"#, Seconds(duration)),
+            None => writeln!(self.fmt, "}}"),
+        };
+    }
+}
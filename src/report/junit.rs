@@ -0,0 +1,189 @@
+use core::fmt::{self, Write};
+use core::time::Duration;
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+
+use super::{Reporter, Seconds, TestOutcome};
+use crate::{ErrorMessage, HookKind, MainResult, TestContext, TestError};
+
+/// Escapes text for use in XML character data and attribute values.
+struct XmlEscape<W>(W);
+
+impl<W: Write> Write for XmlEscape<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            match c {
+                '&' => self.0.write_str("&amp;")?,
+                '<' => self.0.write_str("&lt;")?,
+                '>' => self.0.write_str("&gt;")?,
+                '"' => self.0.write_str("&quot;")?,
+                '\'' => self.0.write_str("&apos;")?,
+                '\t' | '\n' | '\r' => write!(self.0, "&#{};", c as u32)?,
+                // not representable in XML 1.0 at all, unlike the C1 controls
+                '\0'..='\u{1f}' | '\u{fffe}' | '\u{ffff}' => self.0.write_char(char::REPLACEMENT_CHARACTER)?,
+                c => self.0.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Xml<T>(T);
+
+impl<T: fmt::Display> fmt::Display for Xml<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(XmlEscape(fmt), "{}", self.0)
+    }
+}
+
+/// Writes what goes inside a `<testcase>`.
+type Body<'a> = &'a dyn Fn(&mut dyn Write) -> fmt::Result;
+
+/// JUnit XML output.
+///
+/// Under `alloc` the test cases are held back until the run finishes, so the `<testsuite>` can
+/// say how many failed and were skipped. Otherwise they're streamed one `<testcase>` at a time,
+/// under a `<testsuite>` that only knows how many tests there are.
+pub struct JunitReporter<W> {
+    fmt: W,
+    name: &'static str,
+    shuffle_seed: Option<u64>,
+    total: usize,
+    #[cfg(feature = "alloc")]
+    cases: String,
+    /// Hook failures, which only go in once the test cases are done with.
+    system_err: ErrorMessage,
+}
+
+impl<W: Write> JunitReporter<W> {
+    pub fn new(fmt: W) -> Self {
+        Self::with_name(fmt, "mintest")
+    }
+
+    /// Uses `name` for the `<testsuite>` name and each `<testcase>` classname.
+    pub fn with_name(fmt: W, name: &'static str) -> Self {
+        JunitReporter {
+            fmt,
+            name,
+            shuffle_seed: None,
+            total: 0,
+            #[cfg(feature = "alloc")]
+            cases: String::new(),
+            system_err: ErrorMessage::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.fmt
+    }
+
+    /// Where test cases are written.
+    fn cases(&mut self) -> &mut dyn Write {
+        #[cfg(feature = "alloc")]
+        return &mut self.cases;
+
+        #[cfg(not(feature = "alloc"))]
+        &mut self.fmt
+    }
+
+    /// Opens the `<testsuite>`, with totals if the run is over.
+    fn testsuite(&mut self, results: Option<&MainResult>) -> fmt::Result {
+        write!(self.fmt, "  <testsuite name=\"{}\" tests=\"{}\"", Xml(self.name), self.total)?;
+        if let Some(results) = results {
+            write!(self.fmt, " failures=\"{}\" skipped=\"{}\"", results.failed + results.timed_out, results.skipped)?;
+            if let Some(duration) = results.duration {
+                write!(self.fmt, " time=\"{}\"", Seconds(duration))?;
+            }
+        }
+        writeln!(self.fmt, ">")?;
+        if let Some(seed) = self.shuffle_seed {
+            writeln!(self.fmt, "    <properties>")?;
+            writeln!(self.fmt, "      <property name=\"shuffle_seed\" value=\"{}\"/>", seed)?;
+            writeln!(self.fmt, "    </properties>")?;
+        }
+        Ok(())
+    }
+
+    fn testcase(&mut self, context: &TestContext, duration: Option<Duration>, body: Option<Body>) -> fmt::Result {
+        let name = self.name;
+        let cases = self.cases();
+        write!(cases, "    <testcase name=\"{}\" classname=\"{}\"", Xml(context.test.name), Xml(name))?;
+        if let Some(duration) = duration {
+            write!(cases, " time=\"{}\"", Seconds(duration))?;
+        }
+        match body {
+            Some(body) => {
+                writeln!(cases, ">")?;
+                write!(cases, "      ")?;
+                body(cases)?;
+                writeln!(cases)?;
+                writeln!(cases, "    </testcase>")
+            },
+            None => writeln!(cases, "/>"),
+        }
+    }
+}
+
+impl<W: Write> Reporter for JunitReporter<W> {
+    fn run_shuffled(&mut self, seed: u64) {
+        self.shuffle_seed = Some(seed);
+    }
+
+    fn run_started(&mut self, total: usize, _filtered_out: usize) {
+        self.total = total;
+        let _ = writeln!(self.fmt, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+        let _ = writeln!(self.fmt, "<testsuites>");
+        #[cfg(not(feature = "alloc"))]
+        let _ = self.testsuite(None);
+    }
+
+    fn watchdog_armed(&mut self, context: &TestContext, timeout: Duration) {
+        // written straight out, even ahead of the `<testsuite>`, since a reset leaves nothing else
+        // comments can't contain `--` or end in `-`, so fall back on the test's number
+        let name = context.test.name;
+        let _ = match name.contains('-') {
+            true => writeln!(self.fmt, "    <!-- running test {} (watchdog {}s) -->", context.index + 1, Seconds(timeout)),
+            false => writeln!(self.fmt, "    <!-- running {} (watchdog {}s) -->", name, Seconds(timeout)),
+        };
+    }
+
+    fn test_finished(&mut self, context: &TestContext, outcome: &TestOutcome, duration: Option<Duration>) {
+        let _ = match outcome {
+            TestOutcome::Passed(..) => self.testcase(context, duration, None),
+            TestOutcome::Failed(failure) => self.testcase(context, duration, Some(&|fmt: &mut dyn Write| write!(fmt,
+                "<failure message=\"{}\">{}</failure>",
+                Xml(failure), Xml(failure),
+            ))),
+            TestOutcome::TimedOut(timeout) => self.testcase(context, duration, Some(&|fmt: &mut dyn Write| write!(fmt,
+                "<failure type=\"timeout\" message=\"timed out after {}s\"/>",
+                Seconds(*timeout),
+            ))),
+        };
+    }
+
+    fn test_skipped(&mut self, context: &TestContext, reason: Option<&str>) {
+        let _ = match reason {
+            Some(reason) => self.testcase(context, None, Some(&|fmt: &mut dyn Write| write!(fmt, "<skipped message=\"{}\"/>", Xml(reason)))),
+            None => self.testcase(context, None, Some(&|fmt: &mut dyn Write| write!(fmt, "<skipped/>"))),
+        };
+    }
+
+    fn hook_failed(&mut self, kind: HookKind, name: &str, error: &TestError) {
+        let _ = writeln!(self.system_err, "{} hook `{}` failed: {}", kind, name, error);
+    }
+
+    fn run_finished(&mut self, results: &MainResult) {
+        #[cfg(feature = "alloc")]
+        {
+            let _ = self.testsuite(Some(results));
+            let _ = self.fmt.write_str(&self.cases);
+        }
+        #[cfg(not(feature = "alloc"))]
+        let _ = results;
+        if !self.system_err.as_str().is_empty() {
+            let _ = writeln!(self.fmt, "    <system-err>{}</system-err>", Xml(&self.system_err));
+        }
+        let _ = writeln!(self.fmt, "  </testsuite>");
+        let _ = writeln!(self.fmt, "</testsuites>");
+    }
+}
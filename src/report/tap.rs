@@ -0,0 +1,147 @@
+use core::fmt::{self, Write};
+use core::time::Duration;
+
+use super::{Reporter, Seconds, TestOutcome};
+use crate::{HookKind, MainResult, TestContext, TestError};
+
+/// Escapes `#` and `\` so test names can't be mistaken for TAP directives.
+struct Description<'a>(&'a str);
+
+impl<'a> fmt::Display for Description<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '#' | '\\' => {
+                    fmt.write_char('\\')?;
+                    fmt.write_char(c)?
+                },
+                '\n' | '\r' => fmt.write_char(' ')?,
+                c => fmt.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes a double-quoted YAML scalar's contents.
+struct YamlEscape<W>(W);
+
+impl<W: Write> Write for YamlEscape<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            match c {
+                '"' => self.0.write_str("\\\"")?,
+                '\\' => self.0.write_str("\\\\")?,
+                '\n' => self.0.write_str("\\n")?,
+                '\r' => self.0.write_str("\\r")?,
+                '\t' => self.0.write_str("\\t")?,
+                c if c.is_control() => write!(self.0, "\\x{:02x}", c as u32)?,
+                c => self.0.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Keeps every line written a `# ` comment.
+struct Comment<W>(W);
+
+impl<W: Write> Write for Comment<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for (i, line) in s.split('\n').enumerate() {
+            if i > 0 {
+                self.0.write_str("\n# ")?;
+            }
+            self.0.write_str(line.trim_end_matches('\r'))?;
+        }
+        Ok(())
+    }
+}
+
+/// Test Anything Protocol (version 14) output.
+pub struct TapReporter<W> {
+    fmt: W,
+    shuffle_seed: Option<u64>,
+}
+
+impl<W: Write> TapReporter<W> {
+    pub fn new(fmt: W) -> Self {
+        TapReporter {
+            fmt,
+            shuffle_seed: None,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.fmt
+    }
+
+    fn diagnostic(&mut self, message: &dyn fmt::Display, severity: &str, duration: Option<Duration>) -> fmt::Result {
+        writeln!(self.fmt, "  ---")?;
+        write!(self.fmt, "  message: \"")?;
+        write!(YamlEscape(&mut self.fmt), "{}", message)?;
+        writeln!(self.fmt, "\"")?;
+        writeln!(self.fmt, "  severity: {}", severity)?;
+        if let Some(duration) = duration {
+            writeln!(self.fmt, "  duration_ms: {}.{:03}", duration.as_millis(), duration.subsec_micros() % 1000)?;
+        }
+        writeln!(self.fmt, "  ...")
+    }
+}
+
+impl<W: Write> Reporter for TapReporter<W> {
+    fn run_shuffled(&mut self, seed: u64) {
+        self.shuffle_seed = Some(seed);
+    }
+
+    fn run_started(&mut self, total: usize, _filtered_out: usize) {
+        let _ = writeln!(self.fmt, "TAP version 14");
+        let _ = writeln!(self.fmt, "1..{}", total);
+        if let Some(seed) = self.shuffle_seed {
+            let _ = writeln!(self.fmt, "# shuffled with seed {}", seed);
+        }
+    }
+
+    fn watchdog_armed(&mut self, context: &TestContext, timeout: Duration) {
+        let _ = writeln!(self.fmt, "# running {} - {} (watchdog {}s)", context.index + 1, Description(context.test.name), Seconds(timeout));
+    }
+
+    fn test_finished(&mut self, context: &TestContext, outcome: &TestOutcome, duration: Option<Duration>) {
+        let number = context.index + 1;
+        let name = Description(context.test.name);
+        let _ = match (outcome, context.test.expected) {
+            (TestOutcome::Passed(Some(e)), expected) if expected.is_fail() =>
+                writeln!(self.fmt, "not ok {} - {} # TODO expected failure", number, name)
+                    .and_then(|()| self.diagnostic(e, "todo", duration)),
+            (TestOutcome::Passed(..), _) => match duration {
+                Some(duration) => writeln!(self.fmt, "ok {} - {} # time={}s", number, name, Seconds(duration)),
+                None => writeln!(self.fmt, "ok {} - {}", number, name),
+            },
+            (TestOutcome::Failed(failure), _) =>
+                writeln!(self.fmt, "not ok {} - {}", number, name)
+                    .and_then(|()| self.diagnostic(failure, "fail", duration)),
+            (TestOutcome::TimedOut(timeout), _) =>
+                writeln!(self.fmt, "not ok {} - {}", number, name)
+                    .and_then(|()| self.diagnostic(&format_args!("timed out after {}s", Seconds(*timeout)), "fail", duration)),
+        };
+    }
+
+    fn test_skipped(&mut self, context: &TestContext, reason: Option<&str>) {
+        let number = context.index + 1;
+        let name = Description(context.test.name);
+        let _ = match reason {
+            Some(reason) => writeln!(self.fmt, "ok {} - {} # SKIP {}", number, name, Description(reason)),
+            None => writeln!(self.fmt, "ok {} - {} # SKIP", number, name),
+        };
+    }
+
+    fn hook_failed(&mut self, kind: HookKind, name: &str, error: &TestError) {
+        // a YAML block only belongs after a test point, so this is left as comments
+        let _ = write!(Comment(&mut self.fmt), "# {} hook {} failed: {}", kind, Description(name), error)
+            .and_then(|()| writeln!(self.fmt));
+    }
+
+    fn run_finished(&mut self, results: &MainResult) {
+        let _ = writeln!(self.fmt, "# {}", results);
+    }
+}
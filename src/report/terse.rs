@@ -0,0 +1,106 @@
+use core::fmt::Write;
+use core::time::Duration;
+
+use super::{Reporter, Seconds, TestOutcome};
+use crate::{HookKind, MainResult, TestContext, TestError};
+
+/// Characters written on a line before the count of tests run so far, as libtest does.
+const COLUMNS: usize = 88;
+
+/// libtest's terse output: a character per test, with failures spelled out as they happen
+/// rather than gathered up for the end.
+pub struct TerseReporter<W> {
+    fmt: W,
+    shuffle_seed: Option<u64>,
+    total: usize,
+    /// Tests reported so far.
+    done: usize,
+    /// Characters written on the current line.
+    column: usize,
+}
+
+impl<W: Write> TerseReporter<W> {
+    pub fn new(fmt: W) -> Self {
+        TerseReporter {
+            fmt,
+            shuffle_seed: None,
+            total: 0,
+            done: 0,
+            column: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.fmt
+    }
+
+    fn progress(&mut self, c: char) {
+        self.done += 1;
+        self.column += 1;
+        let _ = self.fmt.write_char(c);
+        if self.column == COLUMNS {
+            let _ = writeln!(self.fmt, " {}/{}", self.done, self.total);
+            self.column = 0;
+        }
+    }
+
+    /// Ends the line of progress, so what follows gets a line of its own.
+    fn break_line(&mut self) {
+        if self.column > 0 {
+            let _ = writeln!(self.fmt);
+            self.column = 0;
+        }
+    }
+}
+
+impl<W: Write> Reporter for TerseReporter<W> {
+    fn run_shuffled(&mut self, seed: u64) {
+        self.shuffle_seed = Some(seed);
+    }
+
+    fn run_started(&mut self, total: usize, _filtered_out: usize) {
+        self.total = total;
+        let _ = match self.shuffle_seed {
+            Some(seed) => writeln!(self.fmt, "running {} tests (shuffled with seed {})", total, seed),
+            None => writeln!(self.fmt, "running {} tests", total),
+        };
+    }
+
+    fn watchdog_armed(&mut self, context: &TestContext, timeout: Duration) {
+        // a line of dots wouldn't say which test a reset came in
+        self.break_line();
+        let _ = writeln!(self.fmt, "running {} (watchdog {}s)", context.test.name, Seconds(timeout));
+    }
+
+    fn test_finished(&mut self, context: &TestContext, outcome: &TestOutcome, _duration: Option<Duration>) {
+        let name = context.test.name;
+        match outcome {
+            TestOutcome::Passed(..) => self.progress('.'),
+            TestOutcome::Failed(failure) => {
+                self.progress('F');
+                self.break_line();
+                let _ = writeln!(self.fmt, "{}: {}", name, failure);
+            },
+            TestOutcome::TimedOut(timeout) => {
+                self.progress('F');
+                self.break_line();
+                let _ = writeln!(self.fmt, "{}: still running after {}s", name, Seconds(*timeout));
+            },
+        }
+    }
+
+    fn test_skipped(&mut self, _context: &TestContext, _reason: Option<&str>) {
+        self.progress('i');
+    }
+
+    fn hook_failed(&mut self, kind: HookKind, name: &str, error: &TestError) {
+        self.break_line();
+        let _ = writeln!(self.fmt, "{} hook `{}` failed: {}", kind, name, error);
+    }
+
+    fn run_finished(&mut self, results: &MainResult) {
+        self.break_line();
+        let _ = writeln!(self.fmt);
+        let _ = writeln!(self.fmt, "{}", results);
+    }
+}
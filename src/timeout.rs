@@ -0,0 +1,54 @@
+use core::marker::PhantomData;
+use core::time::Duration;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+
+use super::{Hooks, Ran, RunConfig, TestContext, TestError, run_hooked};
+
+/// Runs a test between its setup and teardown hooks on a thread of its own, reporting it as
+/// timed out if it's still going after `timeout`.
+///
+/// Threads can't be killed, so a test that times out is left running in the background, alongside
+/// whatever runs after it. Its teardown hooks still run once it finishes, on the same thread, so
+/// they never overlap the test itself.
+pub fn run(config: &RunConfig, context: &TestContext, timeout: Duration) -> (Ran, Option<Duration>) {
+    let (sender, receiver) = mpsc::channel();
+    let (test, index, total) = (context.test.clone(), context.index, context.total);
+    // copied, since the thread can outlive the run they're borrowed for
+    let (setup, teardown, clock) = (config.hooks.setup.to_vec(), config.hooks.teardown.to_vec(), config.clock);
+    let start = clock.map(|clock| clock.now());
+    let spawned = thread::Builder::new().name(test.name.into()).spawn(move || {
+        let config = RunConfig {
+            clock,
+            hooks: Hooks {
+                setup: &setup,
+                teardown: &teardown,
+                ..Hooks::default()
+            },
+            ..RunConfig::default()
+        };
+        let context = TestContext {
+            test: &test,
+            index,
+            total,
+            _phantom: PhantomData,
+        };
+        let _ = sender.send(run_hooked(&config, &context));
+    });
+
+    let (result, expired) = match spawned {
+        Err(e) => (Err(TestError::format(format_args!("failed to spawn test thread: {}", e))), None),
+        Ok(..) => match receiver.recv_timeout(timeout) {
+            Ok(ran) => return ran,
+            Err(RecvTimeoutError::Timeout) => (Ok(()), Some(timeout)),
+            Err(RecvTimeoutError::Disconnected) => (Err(TestError::Display(&"test thread exited without a result")), None),
+        },
+    };
+    let elapsed = clock.and_then(|clock| start.map(|start| clock.elapsed(start)));
+    (Ran::Here {
+        setup: None,
+        result,
+        expired,
+        teardown: None,
+    }, elapsed)
+}
@@ -0,0 +1,64 @@
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+
+use super::{TestError, TestResult};
+
+thread_local! {
+    /// `Some(expected)` while this thread is inside `catch`.
+    static CATCHING: Cell<Option<bool>> = const { Cell::new(None) };
+    static LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+pub fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(panic) = payload.downcast_ref::<String>() {
+        panic
+    } else if let Some(panic) = payload.downcast_ref::<&'static str>() {
+        panic
+    } else {
+        "panic"
+    }
+}
+
+/// Installs the panic hook used by `catch`, once per process.
+///
+/// Panics inside a test are recorded rather than printed, except that unexpected ones are
+/// still passed on when `color-backtrace` is enabled, or when panics abort. Panics elsewhere go to
+/// the previous hook.
+pub fn install_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        #[cfg(feature = "color-backtrace")]
+        {
+            use color_backtrace::{install_with_settings, Settings, Verbosity};
+            install_with_settings(Settings::new().verbosity(Verbosity::Medium))
+        }
+
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| match CATCHING.with(Cell::get) {
+            Some(expected) => {
+                let location = info.location().map(|l| l.to_string());
+                LOCATION.with(|l| *l.borrow_mut() = location);
+                // nothing will be left to report it under `panic = "abort"`
+                if cfg!(panic = "abort") || (cfg!(feature = "color-backtrace") && !expected) {
+                    previous(info)
+                }
+            },
+            None => previous(info),
+        }));
+    });
+}
+
+/// Runs a test, turning a panic into `TestError::Panic`.
+///
+/// Calls may nest, as they do for property tests.
+pub fn catch<F: FnOnce() -> TestResult>(expected: bool, f: F) -> TestResult {
+    let outer = CATCHING.with(|c| c.replace(Some(expected)));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    CATCHING.with(|c| c.set(outer));
+    result.unwrap_or_else(|payload| Err(TestError::Panic {
+        payload,
+        location: LOCATION.with(|l| l.borrow_mut().take()),
+    }))
+}
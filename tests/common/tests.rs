@@ -1,4 +1,8 @@
 use mintest::{*, test};
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use core::task::{Context, Poll};
 
 #[test]
 fn empty_test() {
@@ -95,3 +99,442 @@ fn test_multi_attr() {
 fn test_multi_attr_reverse() {
     panic!()
 }
+
+#[test]
+fn test_check() -> TestResult {
+    let value = Some(3);
+    check!(value.is_some());
+    check!(value.is_some(), "value was {:?}", value);
+    check_eq!(value, Some(3));
+    check_ne!(value, None, "value was {:?}", value);
+    check_matches!(value, Some(n) if n > 2);
+    OK
+}
+
+#[test(should_fail)]
+fn test_check_fail() -> TestResult {
+    check!(1 + 1 == 3);
+    OK
+}
+
+#[test(should_fail)]
+fn test_check_eq_fail() -> TestResult {
+    check_eq!(1 + 1, 3);
+    OK
+}
+
+#[test(should_fail)]
+fn test_check_matches_fail() -> TestResult {
+    check_matches!(Some(1), None);
+    OK
+}
+
+#[test]
+fn test_try_unwrap() -> TestResult {
+    let value = try_unwrap!(Some(1));
+    let parsed: u8 = try_expect!("2".parse::<u8>(), "parse failed");
+    check_eq!(value + parsed, 3);
+    check_eq!(Ok::<_, ()>(4).test_unwrap()?, 4);
+    OK
+}
+
+#[test(should_fail)]
+fn test_try_unwrap_none() -> TestResult {
+    try_unwrap!(None::<u8>);
+    OK
+}
+
+#[test(should_fail)]
+fn test_try_expect_err() -> TestResult {
+    try_expect!("x".parse::<u8>(), "parse failed");
+    OK
+}
+
+// only messages stored inline have anywhere to run out of
+#[cfg(not(feature = "alloc"))]
+#[test]
+fn test_message_truncated() -> TestResult {
+    use core::fmt::Write;
+    // the last char doesn't fit whole, so none of it is kept
+    let mut message = ErrorMessage::format(format_args!("{:1$}\u{e9}", "", ErrorMessage::CAPACITY - 1));
+    check!(message.is_truncated());
+    check_eq!(message.as_str().len(), ErrorMessage::CAPACITY - 1);
+    // the message stops where it was cut off, even though there's room left
+    let _ = message.write_str("x");
+    check_eq!(message.as_str().len(), ErrorMessage::CAPACITY - 1);
+    check!(message.as_str().bytes().all(|b| b == b' '));
+    OK
+}
+
+#[cfg(not(feature = "alloc"))]
+#[test]
+fn test_message_operands() -> TestResult {
+    fn fails() -> TestResult {
+        check_eq!(1 + 1, 3);
+        OK
+    }
+    match fails() {
+        Err(TestError::Message(message)) => check!(!message.is_truncated() && message.as_str().ends_with("\n  left: 2\n right: 3")),
+        _ => check!(false, "expected check_eq! to fail"),
+    }
+    OK
+}
+
+#[test(should_panic(expected = "whee"))]
+fn test_panic_expected() {
+    panic!("whee {}", 1)
+}
+
+#[test]
+#[should_panic(expected = "whee")]
+fn test_panic_expected_attr() {
+    panic!("whee")
+}
+
+#[test(should_panic = "whee")]
+fn test_panic_expected_eq() {
+    panic!("whee")
+}
+
+#[allow(dead_code)]
+#[derive(Debug)]
+enum CrcError {
+    Mismatch { expected: u8, actual: u8 },
+    Timeout,
+}
+
+#[test(should_fail = "CRC")]
+fn test_err_expected() -> TestResult {
+    err(&"CRC mismatch")
+}
+
+#[test(should_fail(expected = "mismatch"))]
+fn test_err_expected_paren() -> TestResult {
+    err(&"CRC mismatch")
+}
+
+#[test(should_fail(matches = CrcError::Mismatch { expected: 0x12, .. }))]
+fn test_err_matches() -> Result<(), CrcError> {
+    Err(CrcError::Mismatch { expected: 0x12, actual: 0x34 })
+}
+
+#[test(should_fail(matches = CrcError::Timeout | CrcError::Mismatch { expected: 0..=0x7f, actual: _ }))]
+fn test_err_matches_name(context: TestContext) -> Result<(), CrcError> {
+    let expected = TestExpected::FailMatching("CrcError::Timeout | CrcError::Mismatch { expected: 0..=0x7f, actual: _ }");
+    match context.test.expected == expected {
+        true => Err(CrcError::Timeout),
+        false => Ok(()),
+    }
+}
+
+static INITIALIZED: AtomicBool = AtomicBool::new(false);
+static SETUPS: AtomicUsize = AtomicUsize::new(0);
+static TEARDOWNS: AtomicUsize = AtomicUsize::new(0);
+
+#[before_all]
+fn init() {
+    INITIALIZED.store(true, Ordering::SeqCst);
+}
+
+#[setup]
+fn count_setup(_context: &TestContext) {
+    SETUPS.fetch_add(1, Ordering::SeqCst);
+}
+
+#[teardown]
+fn count_teardown() -> TestResult {
+    TEARDOWNS.fetch_add(1, Ordering::SeqCst);
+    check_eq!(PORTS_OPEN.load(Ordering::SeqCst), 0, "fixtures should be dropped by now");
+    OK
+}
+
+// counts every other test's hooks
+#[test(serial)]
+fn test_hooks() -> TestResult {
+    check!(INITIALIZED.load(Ordering::SeqCst));
+    check_eq!(SETUPS.load(Ordering::SeqCst), TEARDOWNS.load(Ordering::SeqCst) + 1);
+    OK
+}
+
+static PORTS_OPEN: AtomicUsize = AtomicUsize::new(0);
+
+struct Port {
+    owner: &'static str,
+}
+
+impl Fixture for Port {
+    fn build(context: &TestContext) -> Result<Self, TestError> {
+        PORTS_OPEN.fetch_add(1, Ordering::SeqCst);
+        Ok(Port {
+            owner: context.test.name,
+        })
+    }
+}
+
+impl Drop for Port {
+    fn drop(&mut self) {
+        PORTS_OPEN.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[test(serial)]
+fn test_fixture(context: TestContext, port: &mut Port, other: Port) -> TestResult {
+    check_eq!(port.owner, context.test.name);
+    check_eq!(other.owner, context.test.name);
+    check_eq!(PORTS_OPEN.load(Ordering::SeqCst), 2);
+    OK
+}
+
+#[test]
+fn test_context_ref(context: &TestContext, port: &Port) -> TestResult {
+    check_eq!(port.owner, context.test.name);
+    OK
+}
+
+#[test(case(1, 2, 3), case(0, 0, 0), case(u8::MAX, 1, 0, name = "wrapping"))]
+fn test_case_add(a: u8, b: u8, sum: u8) -> TestResult {
+    check_eq!(a.wrapping_add(b), sum);
+    OK
+}
+
+#[test]
+#[case(&[1, 2], 3)]
+#[case(&[], 0)]
+fn test_case_sum(context: TestContext, values: &[u32], sum: u32) -> TestResult {
+    check_eq!(values.iter().sum::<u32>(), sum);
+    check!(context.test.name.starts_with("test_case_sum::case_"));
+    OK
+}
+
+#[test(types(u8, u16, u32))]
+fn test_types_widen<T>() -> TestResult where T: From<u8> + Into<u64> {
+    check_eq!(T::from(200).into(), 200u64);
+    OK
+}
+
+#[test(types(Option<u8>, [u8; 2], (u8, bool), &'static str))]
+fn test_types_name<T: Default>(context: TestContext) -> TestResult {
+    let _ = T::default();
+    let names = ["test_types_name::<Option<u8>>", "test_types_name::<[u8; 2]>", "test_types_name::<(u8, bool)>", "test_types_name::<&'static str>"];
+    check!(names.contains(&context.test.name), "{}", context.test.name);
+    OK
+}
+
+#[test(property, cases = 64)]
+fn test_property_commutes(a: u16, b: u16) -> TestResult {
+    check_eq!(a.wrapping_add(b), b.wrapping_add(a));
+    OK
+}
+
+#[test(property, should_fail = "input: (10,)")]
+fn test_property_shrinks(a: u32) -> TestResult {
+    check!(a < 10);
+    OK
+}
+
+#[test]
+fn test_property_counterexample(context: TestContext) -> TestResult {
+    let result = property::check(&context, 64, Some(42), |a: u32| {
+        check!(a < 10);
+        OK
+    });
+    // even without `alloc`, where the message has little room
+    match result {
+        Err(TestError::Message(message)) => check!(message.as_str().starts_with("input: 10 (seed 0x2a)\n error: "), "{}", message),
+        _ => check!(false, "expected the property to fail"),
+    }
+    OK
+}
+
+#[test(property, should_panic = "too big")]
+fn test_property_panics(a: u32) {
+    assert!(a < 10, "too big");
+}
+
+#[test(property, cases = 16)]
+fn test_property_wide(a: u128) -> TestResult {
+    check!(a >> 64 != 0 || a < 2, "only the low bits were drawn: {:#x}", a);
+    OK
+}
+
+struct YieldOnce(bool);
+
+impl Future for YieldOnce {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        match self.0 {
+            true => Poll::Ready(()),
+            false => {
+                self.0 = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            },
+        }
+    }
+}
+
+#[test]
+async fn test_async(context: TestContext<'_>) -> TestResult {
+    YieldOnce(false).await;
+    check_eq!(context.test.name, "test_async");
+    OK
+}
+
+static EXECUTED: AtomicUsize = AtomicUsize::new(0);
+
+fn counting_executor<F: Future>(future: F) -> F::Output {
+    EXECUTED.fetch_add(1, Ordering::SeqCst);
+    block_on(future)
+}
+
+#[test(executor = counting_executor, serial)]
+async fn test_async_executor(port: &mut Port) -> TestResult {
+    YieldOnce(false).await;
+    check_eq!(EXECUTED.load(Ordering::SeqCst), 1);
+    check_eq!(port.owner, "test_async_executor");
+    OK
+}
+
+#[test(timeout_ms = 5000)]
+fn test_timeout_ms() {
+}
+
+#[test(should_exit = 3)]
+fn test_should_exit() {
+    #[cfg(feature = "std")]
+    std::process::exit(3)
+}
+
+#[test(should_abort)]
+fn test_should_abort() {
+    #[cfg(feature = "std")]
+    std::process::abort()
+}
+
+/// Runs the named tests, which are skipped otherwise, to see how they turn out.
+#[cfg(all(feature = "std", feature = "linkme"))]
+fn run_skipped(names: &[&str], config: RunConfig) -> (MainResult, String) {
+    let tests = names.iter().filter_map(|name| TESTS.iter().find(|test| test.name == *name));
+    let mut out = String::new();
+    let results = test_all(&RunConfig {
+        ignored: RunIgnored::Yes,
+        ..config
+    }, &mut PrettyReporter::new(&mut out), tests);
+    (results, out)
+}
+
+#[test(should_exit = 3, skip = "run by test_termination_mismatch")]
+fn exit_returns() {
+}
+
+#[test(should_exit = 3, skip = "run by test_termination_mismatch")]
+fn exit_wrong_status() {
+    #[cfg(feature = "std")]
+    std::process::exit(4)
+}
+
+#[test(should_abort, skip = "run by test_termination_mismatch")]
+fn abort_returns() {
+}
+
+#[test(should_abort, skip = "run by test_termination_mismatch")]
+fn abort_exits() {
+    #[cfg(feature = "std")]
+    std::process::exit(0)
+}
+
+#[cfg(all(feature = "std", feature = "linkme"))]
+#[test]
+fn test_termination_mismatch() -> TestResult {
+    let (results, out) = run_skipped(&["exit_returns", "exit_wrong_status", "abort_returns", "abort_exits"], RunConfig::default());
+    check_eq!((results.passed, results.failed), (0, 4), "\n{}", out);
+    OK
+}
+
+#[test(resource = "uart0", resource = "spi1")]
+fn test_resources(context: TestContext) -> TestResult {
+    check_eq!(context.test.resources, ["uart0", "spi1"]);
+    OK
+}
+
+#[test(skip = "run by test_isolate")]
+fn isolated_exit() {
+    #[cfg(feature = "std")]
+    std::process::exit(0)
+}
+
+#[test(skip = "run by test_isolate")]
+fn isolated_abort() {
+    #[cfg(feature = "std")]
+    std::process::abort()
+}
+
+#[test(skip = "run by test_isolate")]
+fn isolated_hang() {
+    #[cfg(feature = "std")]
+    std::thread::sleep(core::time::Duration::from_secs(60))
+}
+
+#[cfg(all(feature = "std", feature = "linkme"))]
+#[test]
+fn test_isolate() -> TestResult {
+    // each ends its process, which shouldn't stop the run from getting to the last
+    let (results, out) = run_skipped(&["isolated_abort", "isolated_exit", "isolated_hang", "empty_test"], RunConfig {
+        isolate: true,
+        timeout: Some(core::time::Duration::from_millis(500)),
+        ..RunConfig::default()
+    });
+    check_eq!((results.passed, results.failed, results.timed_out), (1, 2, 1), "\n{}", out);
+    check!(out.contains("empty_test ... \x1b[34mOK"), "\n{}", out);
+    OK
+}
+
+/// Runs this binary with its own command line, giving what it wrote to stdout and stderr.
+#[cfg(all(feature = "std", feature = "linkme"))]
+fn run_self(args: &[&str]) -> (String, String) {
+    let exe = std::env::current_exe().expect("no path to the test binary");
+    let output = std::process::Command::new(exe)
+        .args(args)
+        // not the test an isolated parent wants run
+        .env_remove("MINTEST_CHILD")
+        .output()
+        .expect("couldn't run the test binary");
+    (String::from_utf8_lossy(&output.stdout).into_owned(), String::from_utf8_lossy(&output.stderr).into_owned())
+}
+
+#[cfg(all(feature = "std", feature = "linkme"))]
+#[test]
+fn test_list_shuffled() -> TestResult {
+    let (out, err) = run_self(&["--list", "--exact", "empty_test", "--shuffle=5"]);
+    check_eq!(out, "empty_test: test\n\n1 tests, 0 benchmarks (shuffled with seed 5)\n");
+    check_eq!(err, "");
+    // a terse listing only has room for tests
+    let (out, err) = run_self(&["--list", "--exact", "empty_test", "--shuffle", "-q"]);
+    check_eq!(out, "empty_test: test\n");
+    check!(err.starts_with("shuffled with seed "), "{}", err);
+    OK
+}
+
+#[cfg(all(feature = "std", feature = "linkme"))]
+#[test]
+fn test_format_stdout() -> TestResult {
+    // kept apart from the human-readable formats, for tools to read
+    let (out, err) = run_self(&["--exact", "empty_test", "--format=json"]);
+    check!(out.starts_with("{\"type\":\"suite\",\"event\":\"started\",\"test_count\":1}\n"), "{}", out);
+    check_eq!(err, "");
+    let (out, err) = run_self(&["--exact", "empty_test"]);
+    check_eq!(out, "");
+    check!(err.starts_with("running 1 tests\n"), "{}", err);
+    OK
+}
+
+#[cfg(all(feature = "std", feature = "linkme"))]
+#[test]
+fn test_list_resources() -> TestResult {
+    let (out, _) = run_self(&["--list", "--exact", "test_resources"]);
+    check_eq!(out, "test_resources: test (resources: uart0, spi1)\n\n1 tests, 0 benchmarks\n");
+    let (out, _) = run_self(&["--list", "--exact", "test_resources", "--format=terse"]);
+    check_eq!(out, "test_resources: test\n");
+    OK
+}
@@ -0,0 +1,1156 @@
+#![cfg(all(feature = "std", not(feature = "unstable-test")))]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use mintest::{Clock, Fixture, Platform, Watchdog, WriteHole, HookKind, Hooks, RunHook, TestHook, ErrorMessage, TestResult, TestUnwrap, check, check_eq, check_matches, try_expect, try_unwrap, Args, MainResult, OutputFormat, PrettyReporter, TerseReporter, Reporter, RunConfig, RunIgnored, TestOrder, JsonReporter, JunitReporter, TapReporter, Test, TestContext, TestError, TestExpected, TestFn, TestOutcome, TestStatus, test_all};
+
+fn plain(name: &'static str) -> Test {
+    Test {
+        status: TestStatus::Enable,
+        name,
+        module_path: module_path!(),
+        test: TestFn::Plain(|| ()),
+        expected: TestExpected::Success,
+        timeout: None,
+        serial: false,
+        resources: &[],
+    }
+}
+
+/// Runs tests in the order they're given, rather than sorted by name.
+fn in_order() -> RunConfig<'static> {
+    RunConfig {
+        order: TestOrder::Given,
+        ..RunConfig::default()
+    }
+}
+
+fn suite() -> Vec<Test> {
+    vec![
+        plain("parse_empty"),
+        plain("parse_header"),
+        plain("write_header"),
+        Test {
+            status: TestStatus::Disable,
+            ..plain("parse_disabled")
+        },
+    ]
+}
+
+fn run(config: &RunConfig) -> (String, String) {
+    let mut out = String::new();
+    let results = test_all(config, &mut PrettyReporter::new(&mut out), suite());
+    (out, results.to_string())
+}
+
+#[test]
+fn filter_substring() {
+    let (out, summary) = run(&RunConfig {
+        filters: &["parse"],
+        ..Default::default()
+    });
+    assert!(out.starts_with("running 2 tests\n"));
+    assert!(!out.contains("write_header"));
+    assert_eq!(summary, "test result: ok. 2 passed; 0 failed; 0 skipped; 1 filtered out");
+}
+
+#[test]
+fn filter_exact() {
+    let (out, summary) = run(&RunConfig {
+        filters: &["parse", "write_header"],
+        exact: true,
+        ..Default::default()
+    });
+    assert!(out.starts_with("running 1 tests\n"));
+    assert!(out.contains("write_header"));
+    assert_eq!(summary, "test result: ok. 1 passed; 0 failed; 0 skipped; 2 filtered out");
+}
+
+#[test]
+fn filter_skip() {
+    let (out, summary) = run(&RunConfig {
+        skip: &["header"],
+        ..Default::default()
+    });
+    assert!(out.starts_with("running 1 tests\n"));
+    assert!(out.contains("parse_empty"));
+    assert_eq!(summary, "test result: ok. 1 passed; 0 failed; 0 skipped; 2 filtered out");
+}
+
+#[test]
+fn ignored_only() {
+    let mut out = String::new();
+    let tests = vec![
+        plain("parse_empty"),
+        Test {
+            status: TestStatus::Skip(Some("slow")),
+            ..plain("parse_slow")
+        },
+    ];
+    let results = test_all(&RunConfig {
+        ignored: RunIgnored::Only,
+        ..Default::default()
+    }, &mut PrettyReporter::new(&mut out), tests);
+    assert!(out.starts_with("running 1 tests\n"));
+    assert!(out.contains("parse_slow"));
+    assert_eq!(results.to_string(), "test result: ok. 1 passed; 0 failed; 0 skipped; 1 filtered out");
+}
+
+fn args(args: &[&str]) -> Result<Args, String> {
+    Args::parse(args.iter().map(|&s| s.to_owned()))
+}
+
+#[test]
+fn args_libtest() {
+    let args = args(&["foo", "--exact", "--skip", "bar", "--skip=baz", "--include-ignored", "--test-threads=1", "--format", "terse", "--nocapture"]).unwrap();
+    assert_eq!(args.filters, ["foo"]);
+    assert_eq!(args.skip, ["bar", "baz"]);
+    assert!(args.exact);
+    assert!(args.nocapture);
+    assert!(!args.list);
+    assert_eq!(args.ignored, RunIgnored::Yes);
+    assert_eq!(args.test_threads, Some(1));
+    assert_eq!(args.format, OutputFormat::Terse);
+}
+
+#[test]
+fn args_invalid() {
+    assert!(args(&["--ignored", "--include-ignored"]).is_err());
+    assert!(args(&["--test-threads", "0"]).is_err());
+    assert!(args(&["--skip"]).is_err());
+    assert!(args(&["--format", "xml"]).is_err());
+    assert!(args(&["--frobnicate"]).is_err());
+    assert!(args(&["--timeout-ms", "0"]).is_err());
+}
+
+#[test]
+fn args_timeout() {
+    let args = args(&["--timeout-ms", "1500", "--abort-on-timeout"]).unwrap();
+    assert_eq!(args.timeout, Some(Duration::from_millis(1500)));
+    assert!(args.abort_on_timeout);
+}
+
+#[test]
+fn args_shuffle() {
+    let order = |args: Args| args.with_config(|config| config.order);
+    assert_eq!(order(args(&[]).unwrap()), TestOrder::Sorted);
+    assert_eq!(order(args(&["--shuffle=42"]).unwrap()), TestOrder::Shuffled(42));
+    assert_eq!(order(args(&["--shuffle-seed", "7"]).unwrap()), TestOrder::Shuffled(7));
+    assert!(matches!(order(args(&["--shuffle", "filter"]).unwrap()), TestOrder::Shuffled(..)));
+    assert!(args(&["--shuffle=-1"]).is_err());
+}
+
+#[test]
+fn args_isolate() {
+    let args = args(&["--isolate"]).unwrap();
+    assert!(args.isolate);
+    args.with_config(|config| assert!(config.isolate));
+}
+
+#[test]
+fn isolate_without_run_tests_from_args() {
+    // libtest runs this binary, so a test's own process never gets to mintest
+    let mut out = String::new();
+    let results = test_all(&RunConfig {
+        isolate: true,
+        ..RunConfig::default()
+    }, &mut PrettyReporter::new(&mut out), &[plain("parse_header")]);
+    assert_eq!(results.failed, 1);
+    assert!(out.contains("never ran the test"), "{}", out);
+}
+
+#[derive(Default)]
+struct Events(Vec<String>);
+
+impl Reporter for Events {
+    fn run_started(&mut self, total: usize, filtered_out: usize) {
+        self.0.push(format!("started {} {}", total, filtered_out));
+    }
+
+    fn test_started(&mut self, context: &TestContext) {
+        self.0.push(format!("test {}/{} {}", context.index, context.total, context.test.name));
+    }
+
+    fn test_finished(&mut self, context: &TestContext, outcome: &TestOutcome, _duration: Option<Duration>) {
+        self.0.push(match outcome {
+            TestOutcome::Passed(..) => format!("ok {}", context.test.name),
+            TestOutcome::Failed(failure) => format!("failed {}: {}", context.test.name, failure),
+            TestOutcome::TimedOut(timeout) => format!("timed out {} {:?}", context.test.name, timeout),
+        });
+    }
+
+    fn test_skipped(&mut self, context: &TestContext, reason: Option<&str>) {
+        self.0.push(format!("skipped {} {:?}", context.test.name, reason));
+    }
+
+    fn hook_failed(&mut self, kind: HookKind, name: &str, error: &TestError) {
+        self.0.push(format!("hook failed {} {}: {}", kind, name, error));
+    }
+
+    fn run_finished(&mut self, results: &MainResult) {
+        self.0.push(format!("finished {}", results.succeeded()));
+    }
+}
+
+#[test]
+fn reporter_events() {
+    let tests = vec![
+        plain("passes"),
+        Test {
+            expected: TestExpected::Fail,
+            ..plain("should_fail_passes")
+        },
+        Test {
+            status: TestStatus::Skip(Some("slow")),
+            ..plain("skipped")
+        },
+    ];
+    let mut events = Events::default();
+    let results = test_all(&RunConfig::default(), &mut events, tests);
+    assert!(!results.succeeded());
+    assert_eq!(events.0, [
+        "started 3 0",
+        "test 0/3 passes",
+        "ok passes",
+        "test 1/3 should_fail_passes",
+        "failed should_fail_passes: expected failure but test passed",
+        "skipped skipped Some(\"slow\")",
+        "finished false",
+    ]);
+}
+
+fn failing(name: &'static str, expected: TestExpected) -> Test {
+    Test {
+        test: TestFn::Static(|_| Err(TestError::Display(&"bad \"crc\"\nvalue"))),
+        expected,
+        ..plain(name)
+    }
+}
+
+#[test]
+fn terse_output() {
+    let tests = vec![
+        plain("passes"),
+        failing("fails", TestExpected::Success),
+        Test {
+            status: TestStatus::Skip(Some("slow")),
+            ..plain("skipped")
+        },
+        plain("passes_again"),
+    ];
+    let mut out = String::new();
+    test_all(&in_order(), &mut TerseReporter::new(&mut out), tests);
+    assert_eq!(out, "\
+running 4 tests
+.F
+fails: bad \"crc\"
+value
+i.
+
+test result: FAILED. 2 passed; 1 failed; 1 skipped; 0 filtered out
+");
+
+    // long runs are broken into lines, each ending with how far along the run is
+    let mut out = String::new();
+    test_all(&RunConfig::default(), &mut TerseReporter::new(&mut out), vec![plain("passes"); 89]);
+    assert_eq!(out, format!("running 89 tests\n{} 88/89\n.\n\ntest result: ok. 89 passed; 0 failed; 0 skipped; 0 filtered out\n", ".".repeat(88)));
+}
+
+#[test]
+fn tap_output() {
+    let tests = vec![
+        plain("passes"),
+        failing("fails", TestExpected::Success),
+        failing("should_fail", TestExpected::Fail),
+        Test {
+            status: TestStatus::Skip(Some("slow # really")),
+            ..plain("skipped")
+        },
+    ];
+    let after_all = [RunHook {
+        name: "deinit",
+        hook: || Err(TestError::Display(&"bus\nstuck")),
+    }];
+    let config = RunConfig {
+        hooks: Hooks {
+            after_all: &after_all,
+            ..Hooks::default()
+        },
+        ..in_order()
+    };
+    let mut out = String::new();
+    test_all(&config, &mut TapReporter::new(&mut out), tests);
+    assert_eq!(out, "\
+TAP version 14
+1..4
+ok 1 - passes
+not ok 2 - fails
+  ---
+  message: \"bad \\\"crc\\\"\\nvalue\"
+  severity: fail
+  ...
+not ok 3 - should_fail # TODO expected failure
+  ---
+  message: \"bad \\\"crc\\\"\\nvalue\"
+  severity: todo
+  ...
+ok 4 - skipped # SKIP slow \\# really
+# after_all hook deinit failed: bus
+# stuck
+# test result: FAILED. 2 passed; 1 failed; 1 skipped; 0 filtered out; 1 hooks failed
+");
+}
+
+#[test]
+fn junit_output() {
+    let tests = vec![
+        plain("passes"),
+        failing("fails", TestExpected::Success),
+        failing("should_fail", TestExpected::Fail),
+        Test {
+            status: TestStatus::Skip(Some("needs <uart>")),
+            ..plain("skipped")
+        },
+        Test {
+            status: TestStatus::Skip(None),
+            ..plain("a&b")
+        },
+    ];
+    let after_all = [RunHook {
+        name: "deinit",
+        hook: || Err(TestError::Display(&"bus <stuck>")),
+    }];
+    let config = RunConfig {
+        hooks: Hooks {
+            after_all: &after_all,
+            ..Hooks::default()
+        },
+        ..in_order()
+    };
+    let mut out = String::new();
+    test_all(&config, &mut JunitReporter::new(&mut out), tests);
+    assert_eq!(out, r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites>
+  <testsuite name="mintest" tests="5" failures="1" skipped="2">
+    <testcase name="passes" classname="mintest"/>
+    <testcase name="fails" classname="mintest">
+      <failure message="bad &quot;crc&quot;&#10;value">bad &quot;crc&quot;&#10;value</failure>
+    </testcase>
+    <testcase name="should_fail" classname="mintest"/>
+    <testcase name="skipped" classname="mintest">
+      <skipped message="needs &lt;uart&gt;"/>
+    </testcase>
+    <testcase name="a&amp;b" classname="mintest">
+      <skipped/>
+    </testcase>
+    <system-err>after_all hook `deinit` failed: bus &lt;stuck&gt;&#10;</system-err>
+  </testsuite>
+</testsuites>
+"#);
+
+    // C0 controls can't be written in XML 1.0, but C1 controls can
+    let mut out = String::new();
+    test_all(&in_order(), &mut JunitReporter::new(&mut out), vec![plain("bell\u{7}\u{85}")]);
+    assert!(out.contains("<testcase name=\"bell\u{fffd}\u{85}\""), "{}", out);
+}
+
+#[test]
+fn json_output() {
+    let tests = vec![
+        plain("passes"),
+        failing("fails", TestExpected::Success),
+        Test {
+            status: TestStatus::Skip(Some("slow")),
+            ..plain("skipped")
+        },
+    ];
+    let mut out = String::new();
+    test_all(&in_order(), &mut JsonReporter::new(&mut out), tests);
+    assert_eq!(out, r#"{"type":"suite","event":"started","test_count":3}
+{"type":"test","event":"started","name":"passes"}
+{"type":"test","name":"passes","event":"ok"}
+{"type":"test","event":"started","name":"fails"}
+{"type":"test","name":"fails","event":"failed","stdout":"bad \"crc\"\nvalue\n"}
+{"type":"test","name":"skipped","event":"ignored","message":"slow"}
+{"type":"suite","event":"failed","passed":1,"failed":1,"ignored":1,"measured":0,"filtered_out":0}
+"#);
+
+    // libtest has no count of its own for timeouts, so they're counted among the failures
+    let hangs = Test {
+        test: TestFn::Plain(|| std::thread::sleep(Duration::from_secs(60))),
+        timeout: Some(Duration::from_millis(20)),
+        ..plain("hangs")
+    };
+    let mut out = String::new();
+    test_all(&in_order(), &mut JsonReporter::new(&mut out), vec![hangs]);
+    assert!(out.ends_with(r#"{"type":"suite","event":"failed","passed":0,"failed":1,"ignored":0,"measured":0,"filtered_out":0}
+"#), "{}", out);
+}
+
+#[test]
+fn timing() {
+    static mut TICKS: u64 = 0;
+    fn tick() {
+        unsafe { TICKS += 250 }
+    }
+    let tests = vec![
+        Test {
+            test: TestFn::Plain(tick),
+            ..plain("quarter")
+        },
+        Test {
+            test: TestFn::Plain(|| unsafe { TICKS += 2000 }),
+            ..plain("two")
+        },
+    ];
+    let config = RunConfig {
+        clock: Some(Clock {
+            ticks: || unsafe { TICKS },
+            frequency: 1000,
+        }),
+        ..Default::default()
+    };
+    let mut out = String::new();
+    let results = test_all(&config, &mut PrettyReporter::new(&mut out), tests);
+    assert_eq!(results.duration, Some(Duration::from_millis(2250)));
+    assert!(out.contains("quarter ... \x1b[34mOK\x1b[0m <0.250s>\n"));
+    assert!(out.contains("two ... \x1b[34mOK\x1b[0m <2.000s>\n"));
+    assert!(out.ends_with("0 filtered out; finished in 2.25s\n"));
+    // truncated rather than rounded
+    let results = MainResult {
+        duration: Some(Duration::new(61, 49_999_999)),
+        ..results
+    };
+    assert!(results.to_string().ends_with("; finished in 61.04s"));
+
+    let stopped = Clock {
+        ticks: || 0,
+        frequency: 0,
+    };
+    assert_eq!(stopped.duration(1000), Duration::ZERO);
+}
+
+fn check_eq_fails() -> TestResult {
+    let left = 2;
+    check_eq!(left + 1, 4, "with {}", "context");
+    Ok(())
+}
+
+#[test]
+fn check_messages() {
+    let line = line!() - 6;
+    assert_eq!(check_eq_fails().unwrap_err().to_string(), format!("\
+tests/runner.rs:{}: check failed: `left + 1 == 4`: with context
+  left: 3
+ right: 4", line));
+
+    let matches = || -> TestResult {
+        check_matches!(Some("x"), None);
+        Ok(())
+    };
+    assert!(matches().unwrap_err().to_string().ends_with("check failed: `Some(\"x\")` matches `None`\n value: Some(\"x\")"));
+
+    // std builds have alloc, so messages are never truncated
+    let long = || -> TestResult {
+        check!(false, "{:1$}", "", ErrorMessage::CAPACITY);
+        Ok(())
+    };
+    assert!(long().unwrap_err().to_string().ends_with(&" ".repeat(ErrorMessage::CAPACITY)));
+}
+
+#[test]
+fn unwrap_messages() {
+    fn parse(s: &str) -> Result<u8, TestError> {
+        let value: u8 = try_unwrap!(s.parse::<u8>());
+        Ok(try_expect!(value.checked_sub(1), "underflow"))
+    }
+    let line = line!() - 3;
+    assert_eq!(parse("x").unwrap_err().to_string(),
+        format!("tests/runner.rs:{}:25: `s.parse::<u8>()` failed: ParseIntError {{ kind: InvalidDigit }}", line));
+    assert_eq!(parse("0").unwrap_err().to_string(), format!("tests/runner.rs:{}:12: underflow", line + 1));
+    assert_eq!(parse("2").ok(), Some(1));
+    let none = None::<()>.test_unwrap().unwrap_err().to_string();
+    assert_eq!(none, format!("tests/runner.rs:{}:27: {}", line!() - 1, TestError::none_error()));
+}
+
+#[test]
+fn panics_fail_tests() {
+    let line = line!() + 3;
+    let tests = vec![
+        Test {
+            test: TestFn::Plain(|| panic!("boom")),
+            ..plain("panics")
+        },
+        Test {
+            test: TestFn::Plain(|| panic!("boom")),
+            expected: TestExpected::Fail,
+            ..plain("should_fail_panics")
+        },
+        plain("passes"),
+    ];
+    let mut events = Events::default();
+    let results = test_all(&in_order(), &mut events, tests);
+    assert_eq!(results.to_string(), "test result: FAILED. 1 passed; 2 failed; 0 skipped; 0 filtered out");
+    assert_eq!(events.0[2], format!("failed panics: panicked at tests/runner.rs:{}:36: boom", line));
+    assert_eq!(events.0[4], format!("failed should_fail_panics: expected failure, got panicked at tests/runner.rs:{}:36: boom", line + 4));
+}
+
+#[test]
+fn panic_expected_message() {
+    let tests = vec![
+        Test {
+            test: TestFn::Plain(|| panic!("bad crc")),
+            expected: TestExpected::PanicWith("crc"),
+            ..plain("matches")
+        },
+        Test {
+            test: TestFn::Plain(|| panic!("timeout")),
+            expected: TestExpected::PanicWith("crc"),
+            ..plain("mismatch")
+        },
+    ];
+    let mut events = Events::default();
+    test_all(&RunConfig::default(), &mut events, tests);
+    assert_eq!(events.0[2], "ok matches");
+    assert!(events.0[4].starts_with("failed mismatch: expected panic containing \"crc\", got panicked at tests/runner.rs:"));
+    assert!(events.0[4].ends_with(": timeout"));
+}
+
+#[test]
+fn fail_expected_error() {
+    let tests = vec![
+        Test {
+            expected: TestExpected::FailWith("crc\"\nv"),
+            ..failing("contains", TestExpected::Success)
+        },
+        Test {
+            expected: TestExpected::FailWith("timeout"),
+            ..failing("wrong_error", TestExpected::Success)
+        },
+        Test {
+            test: TestFn::Static(|_| Err(TestError::Mismatch(ErrorMessage::format(format_args!("Timeout"))))),
+            expected: TestExpected::FailMatching("Error::Crc { .. }"),
+            ..plain("wrong_pattern")
+        },
+        Test {
+            expected: TestExpected::FailMatching("Error::Crc { .. }"),
+            ..plain("no_error")
+        },
+    ];
+    let mut events = Events::default();
+    test_all(&in_order(), &mut events, tests);
+    assert_eq!(events.0[1..events.0.len() - 1], [
+        "test 0/4 contains",
+        "ok contains",
+        "test 1/4 wrong_error",
+        "failed wrong_error: expected failure containing \"timeout\", got bad \"crc\"\nvalue",
+        "test 2/4 wrong_pattern",
+        "failed wrong_pattern: expected failure matching `Error::Crc { .. }`, got Timeout",
+        "test 3/4 no_error",
+        "failed no_error: expected failure matching `Error::Crc { .. }` but test passed",
+    ]);
+}
+
+thread_local! {
+    static HOOK_LOG: std::cell::RefCell<Vec<String>> = const { std::cell::RefCell::new(Vec::new()) };
+}
+
+fn log_hook(entry: String) {
+    HOOK_LOG.with(|log| log.borrow_mut().push(entry));
+}
+
+#[test]
+fn hooks() {
+    let setup = [TestHook {
+        name: "power_on",
+        hook: |context| {
+            log_hook(format!("setup {}", context.test.name));
+            match context.test.name {
+                "no_power" => Err(TestError::Display(&"brownout")),
+                _ => Ok(()),
+            }
+        },
+    }];
+    let teardown = [TestHook {
+        name: "power_off",
+        hook: |context| {
+            log_hook(format!("teardown {}", context.test.name));
+            match context.test.name {
+                "err" => Err(TestError::Display(&"still powered")),
+                _ => Ok(()),
+            }
+        },
+    }];
+    let before_all = [RunHook {
+        name: "init",
+        hook: || {
+            log_hook("before_all".into());
+            Ok(())
+        },
+    }];
+    let after_all = [RunHook {
+        name: "deinit",
+        hook: || {
+            log_hook("after_all".into());
+            Err(TestError::Display(&"bus stuck"))
+        },
+    }];
+    let config = RunConfig {
+        hooks: Hooks {
+            setup: &setup,
+            teardown: &teardown,
+            before_all: &before_all,
+            after_all: &after_all,
+        },
+        ..in_order()
+    };
+    let tests = vec![
+        plain("ok"),
+        failing("err", TestExpected::Success),
+        Test {
+            test: TestFn::Plain(|| panic!("whee")),
+            ..plain("panics")
+        },
+        Test {
+            test: TestFn::Plain(|| log_hook("no_power ran".into())),
+            ..plain("no_power")
+        },
+    ];
+    let mut events = Events::default();
+    let results = test_all(&config, &mut events, tests);
+    assert_eq!(HOOK_LOG.with(|log| log.borrow().clone()), [
+        "before_all",
+        "setup ok", "teardown ok",
+        "setup err", "teardown err",
+        "setup panics", "teardown panics",
+        "setup no_power", "teardown no_power",
+        "after_all",
+    ]);
+    assert_eq!(events.0[4..6], [
+        "failed err: bad \"crc\"\nvalue",
+        "hook failed teardown power_off: still powered",
+    ]);
+    assert_eq!(events.0[9..], [
+        "failed no_power: setup `power_on` failed: brownout",
+        "hook failed after_all deinit: bus stuck",
+        "finished false",
+    ]);
+    assert_eq!(results.to_string(), "test result: FAILED. 1 passed; 3 failed; 0 skipped; 0 filtered out; 1 hooks failed");
+}
+
+#[test]
+fn before_all_failure() {
+    let before_all = [RunHook {
+        name: "init",
+        hook: || Err(TestError::Display(&"no probe")),
+    }];
+    let config = RunConfig {
+        hooks: Hooks {
+            before_all: &before_all,
+            ..Hooks::default()
+        },
+        ..RunConfig::default()
+    };
+    let mut events = Events::default();
+    let results = test_all(&config, &mut events, vec![Test {
+        test: TestFn::Plain(|| panic!("shouldn't run")),
+        ..plain("ok")
+    }]);
+    assert_eq!(events.0, [
+        "started 1 0",
+        "hook failed before_all init: no probe",
+        "skipped ok Some(\"before_all hook failed\")",
+        "finished false",
+    ]);
+    assert_eq!((results.skipped, results.failed, results.hooks_failed), (1, 0, 1));
+}
+
+struct Unplugged;
+
+impl Fixture for Unplugged {
+    fn build(_context: &TestContext) -> Result<Self, TestError> {
+        Err(TestError::Display(&"no such device"))
+    }
+}
+
+#[mintest::test]
+fn open_uart(_uart: Unplugged) {
+}
+
+#[test]
+fn fixture_failure() {
+    let config = RunConfig {
+        filters: &["open_uart"],
+        ..RunConfig::default()
+    };
+    let mut events = Events::default();
+    test_all(&config, &mut events, mintest::TESTS.iter());
+    assert_eq!(events.0[2], "failed open_uart: setup `_uart` failed: no such device");
+}
+
+#[mintest::test(case(1, 2, 3), case(2, 2, 5, name = "wrong"))]
+fn add(a: u8, b: u8, sum: u8) -> TestResult {
+    check_eq!(a + b, sum);
+    Ok(())
+}
+
+#[test]
+fn cases() {
+    let config = RunConfig {
+        filters: &["add::"],
+        ..RunConfig::default()
+    };
+    let mut events = Events::default();
+    let results = test_all(&config, &mut events, mintest::TESTS.iter());
+    assert_eq!((results.passed, results.failed), (1, 1));
+    assert!(events.0.iter().any(|e| e == "ok add::case_1"));
+    assert!(events.0.iter().any(|e| e.starts_with("failed add::wrong: tests/runner.rs:") && e.ends_with("check failed: `a + b == sum`\n  left: 4\n right: 5")));
+}
+
+#[mintest::test(types(u8, i16), case(1), case(-1, name = "negative"))]
+fn narrow<T: std::convert::TryFrom<i32>>(value: i32) -> TestResult {
+    try_unwrap!(T::try_from(value).ok());
+    Ok(())
+}
+
+#[test]
+fn types() {
+    let config = RunConfig {
+        filters: &["narrow::"],
+        ..RunConfig::default()
+    };
+    let mut events = Events::default();
+    test_all(&config, &mut events, mintest::TESTS.iter());
+    let mut outcomes: Vec<_> = events.0.iter().filter(|e| !e.starts_with("test ")).map(|e| e.split(": ").next().unwrap()).collect();
+    outcomes.sort();
+    assert_eq!(outcomes, [
+        "failed narrow::<u8>::negative",
+        "finished false",
+        "ok narrow::<i16>::case_1",
+        "ok narrow::<i16>::negative",
+        "ok narrow::<u8>::case_1",
+        "started 4 3",
+    ]);
+}
+
+#[test]
+fn property_shrinking() {
+    fn property(context: TestContext) -> TestResult {
+        mintest::property::check(&context, 256, Some(42), |(a, b): (u32, bool)| {
+            check!(a < 1000 || !b);
+            Ok(())
+        })
+    }
+
+    fn panics(context: TestContext) -> TestResult {
+        mintest::property::check(&context, 256, None, |value: [u8; 2]| {
+            assert!(value[1] < 100, "too big");
+            Ok(())
+        })
+    }
+
+    let tests = vec![
+        Test {
+            test: TestFn::Static(property),
+            ..plain("property")
+        },
+        Test {
+            test: TestFn::Static(panics),
+            ..plain("panics")
+        },
+    ];
+    let mut events = Events::default();
+    test_all(&in_order(), &mut events, &tests);
+    let mut again = Events::default();
+    test_all(&in_order(), &mut again, &tests);
+    assert_eq!(events.0, again.0);
+
+    assert!(events.0[2].starts_with("failed property: input: (1000, true) (seed 0x2a)\n error: tests/runner.rs:"));
+    assert!(events.0[2].contains("\n failed after "));
+    assert!(events.0[4].starts_with(&format!("failed panics: input: [0, 100] (seed {:#x})\n error: panicked at tests/runner.rs:", mintest::property::seed("panics"))));
+    assert!(events.0[4].contains(": too big\n failed after "));
+}
+
+#[test]
+fn timeouts() {
+    let tests = || vec![
+        Test {
+            test: TestFn::Plain(|| std::thread::sleep(Duration::from_secs(60))),
+            timeout: Some(Duration::from_millis(20)),
+            ..plain("hangs")
+        },
+        Test {
+            timeout: Some(Duration::from_secs(60)),
+            ..plain("quick")
+        },
+        Test {
+            test: TestFn::Plain(|| panic!("whee")),
+            expected: TestExpected::PanicWith("whee"),
+            ..plain("panics")
+        },
+        failing("fails", TestExpected::Success),
+    ];
+    let config = RunConfig {
+        timeout: Some(Duration::from_secs(60)),
+        ..in_order()
+    };
+    let mut events = Events::default();
+    let results = test_all(&config, &mut events, tests());
+    assert_eq!(events.0[2], "timed out hangs 20ms");
+    assert_eq!(events.0[4], "ok quick");
+    assert_eq!(events.0[6], "ok panics");
+    assert_eq!(events.0[8], "failed fails: bad \"crc\"\nvalue");
+    assert_eq!(results.to_string(), "test result: FAILED. 2 passed; 1 failed; 0 skipped; 0 filtered out; 1 timed out");
+
+    let config = RunConfig {
+        abort_on_timeout: true,
+        ..config
+    };
+    let mut events = Events::default();
+    let results = test_all(&config, &mut events, tests());
+    assert_eq!(events.0[1..], [
+        "test 0/4 hangs",
+        "timed out hangs 20ms",
+        "skipped quick Some(\"run aborted after a timeout\")",
+        "skipped panics Some(\"run aborted after a timeout\")",
+        "skipped fails Some(\"run aborted after a timeout\")",
+        "finished false",
+    ]);
+    assert_eq!((results.passed, results.skipped, results.timed_out), (0, 3, 1));
+}
+
+#[test]
+fn test_threads_abort() {
+    let tests = vec![
+        Test {
+            test: TestFn::Plain(|| std::thread::sleep(Duration::from_secs(60))),
+            timeout: Some(Duration::from_millis(200)),
+            ..plain("hangs")
+        },
+        // started on another worker while `hangs` runs
+        failing("fails", TestExpected::Success),
+        // never started, since the workers leave it for the run to get to
+        Test {
+            serial: true,
+            ..plain("serial")
+        },
+        plain("last"),
+    ];
+    let config = RunConfig {
+        test_threads: Some(2),
+        abort_on_timeout: true,
+        ..in_order()
+    };
+    let mut events = Events::default();
+    let results = test_all(&config, &mut events, tests);
+    assert_eq!(events.0[1..], [
+        "test 0/4 hangs",
+        "timed out hangs 200ms",
+        "test 1/4 fails",
+        "failed fails: bad \"crc\"\nvalue",
+        "skipped serial Some(\"run aborted after a timeout\")",
+        "skipped last Some(\"run aborted after a timeout\")",
+        "finished false",
+    ]);
+    assert_eq!((results.failed, results.skipped, results.timed_out), (1, 2, 1));
+}
+
+static HOOK_THREADS: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+
+fn log_thread(hook: &str) -> TestResult {
+    let thread = std::thread::current();
+    HOOK_THREADS.lock().unwrap().push(format!("{} {}", hook, thread.name().unwrap_or_default()));
+    Ok(())
+}
+
+#[test]
+fn timeout_hooks() {
+    let setup = [TestHook {
+        name: "setup",
+        hook: |_| log_thread("setup"),
+    }];
+    let teardown = [TestHook {
+        name: "teardown",
+        hook: |_| log_thread("teardown"),
+    }];
+    let tests = [Test {
+        test: TestFn::Plain(|| log_thread("test").unwrap()),
+        timeout: Some(Duration::from_secs(60)),
+        ..plain("spi_transfer")
+    }];
+    let config = RunConfig {
+        hooks: Hooks {
+            setup: &setup,
+            teardown: &teardown,
+            ..Hooks::default()
+        },
+        ..RunConfig::default()
+    };
+    assert!(test_all(&config, &mut Events::default(), &tests).succeeded());
+    assert_eq!(*HOOK_THREADS.lock().unwrap(), [
+        "setup spi_transfer",
+        "test spi_transfer",
+        "teardown spi_transfer",
+    ]);
+}
+
+thread_local! {
+    static WATCHDOG_LOG: std::cell::RefCell<Vec<String>> = const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// Only has a watchdog, which records what it was asked to do.
+struct SimulatedWatchdog;
+
+impl Platform for SimulatedWatchdog {
+    type Stderr = WriteHole;
+
+    fn exit() -> ! {
+        unreachable!()
+    }
+
+    fn abort() -> ! {
+        unreachable!()
+    }
+
+    fn stderr() -> WriteHole {
+        WriteHole
+    }
+
+    fn watchdog() -> Option<Watchdog> {
+        fn log(entry: String) {
+            WATCHDOG_LOG.with(|log| log.borrow_mut().push(entry));
+        }
+
+        Some(Watchdog {
+            arm: |timeout| log(format!("arm {:?}", timeout)),
+            feed: || log("feed".into()),
+            disarm: || log("disarm".into()),
+        })
+    }
+}
+
+#[test]
+fn watchdog() {
+    let tests = vec![
+        Test {
+            timeout: Some(Duration::from_millis(500)),
+            ..plain("uart_loopback")
+        },
+        plain("no_timeout"),
+    ];
+    let config = RunConfig {
+        watchdog: SimulatedWatchdog::watchdog(),
+        ..in_order()
+    };
+    let mut out = String::new();
+    test_all(&config, &mut TapReporter::new(&mut out), &tests);
+    assert_eq!(WATCHDOG_LOG.with(|log| log.borrow().clone()), [
+        "arm 500ms",
+        "disarm",
+        "feed",
+        "feed",
+    ]);
+    assert_eq!(out.lines().skip(2).take(3).collect::<Vec<_>>(), [
+        "# running 1 - uart_loopback (watchdog 0.500s)",
+        "ok 1 - uart_loopback",
+        "ok 2 - no_timeout",
+    ]);
+
+    let tests = vec![plain("crc"), tests[0].clone(), plain("no_timeout")];
+    let mut out = String::new();
+    test_all(&config, &mut TerseReporter::new(&mut out), tests);
+    assert_eq!(out, "\
+running 3 tests
+.
+running uart_loopback (watchdog 0.500s)
+..
+
+test result: ok. 3 passed; 0 failed; 0 skipped; 0 filtered out
+");
+}
+
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+static MOST_RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+fn busy() -> usize {
+    let running = RUNNING.fetch_add(1, Ordering::SeqCst) + 1;
+    MOST_RUNNING.fetch_max(running, Ordering::SeqCst);
+    std::thread::sleep(Duration::from_millis(20));
+    RUNNING.fetch_sub(1, Ordering::SeqCst);
+    running
+}
+
+#[test]
+fn test_threads() {
+    let parallel = |name| Test {
+        test: TestFn::Plain(|| {
+            busy();
+        }),
+        ..plain(name)
+    };
+    static SERIAL_RAN: AtomicUsize = AtomicUsize::new(0);
+    let tests = vec![
+        parallel("first"),
+        parallel("second"),
+        parallel("third"),
+        Test {
+            test: TestFn::Plain(|| {
+                assert_eq!(busy(), 1);
+                SERIAL_RAN.fetch_add(1, Ordering::SeqCst);
+            }),
+            serial: true,
+            ..plain("serial")
+        },
+        Test {
+            status: TestStatus::Skip(None),
+            ..plain("skipped")
+        },
+        // tests after a serial one wait for it rather than it waiting for them
+        Test {
+            test: TestFn::Plain(|| assert_eq!(SERIAL_RAN.load(Ordering::SeqCst), 1)),
+            ..plain("fourth")
+        },
+        failing("fails", TestExpected::Success),
+    ];
+    let config = RunConfig {
+        test_threads: Some(3),
+        ..in_order()
+    };
+    let mut events = Events::default();
+    let results = test_all(&config, &mut events, tests);
+    assert_eq!(events.0[1..], [
+        "test 0/7 first",
+        "ok first",
+        "test 1/7 second",
+        "ok second",
+        "test 2/7 third",
+        "ok third",
+        "test 3/7 serial",
+        "ok serial",
+        "skipped skipped None",
+        "test 5/7 fourth",
+        "ok fourth",
+        "test 6/7 fails",
+        "failed fails: bad \"crc\"\nvalue",
+        "finished false",
+    ]);
+    assert_eq!(results.to_string(), "test result: FAILED. 5 passed; 1 failed; 1 skipped; 0 filtered out");
+    assert!(MOST_RUNNING.load(Ordering::SeqCst) > 1);
+}
+
+#[test]
+fn test_threads_duration() {
+    static EPOCH: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+    let sleeps = |name| Test {
+        test: TestFn::Plain(|| std::thread::sleep(Duration::from_millis(200))),
+        ..plain(name)
+    };
+    let config = RunConfig {
+        clock: Some(Clock {
+            ticks: || EPOCH.get_or_init(std::time::Instant::now).elapsed().as_nanos() as u64,
+            frequency: 1_000_000_000,
+        }),
+        test_threads: Some(3),
+        ..RunConfig::default()
+    };
+    let results = test_all(&config, &mut Events::default(), vec![sleeps("first"), sleeps("second"), sleeps("third")]);
+    // the time the run took, rather than the 600ms spent in tests
+    let duration = results.duration.unwrap();
+    assert!(duration >= Duration::from_millis(200) && duration < Duration::from_millis(500), "{:?}", duration);
+}
+
+static UART0: AtomicUsize = AtomicUsize::new(0);
+
+fn uart0() {
+    assert_eq!(UART0.fetch_add(1, Ordering::SeqCst), 0, "uart0 already in use");
+    std::thread::sleep(Duration::from_millis(10));
+    UART0.fetch_sub(1, Ordering::SeqCst);
+}
+
+static SAW_UART0: AtomicUsize = AtomicUsize::new(0);
+
+/// Waits a while for another test to be using uart0, which one claiming nothing needn't wait on.
+fn beside_uart0() {
+    for _ in 0..1000 {
+        if UART0.load(Ordering::SeqCst) > 0 {
+            SAW_UART0.fetch_add(1, Ordering::SeqCst);
+            return
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn resources() {
+    let claims = |name| Test {
+        test: TestFn::Plain(uart0),
+        resources: &["uart0"],
+        ..plain(name)
+    };
+    let tests = vec![
+        claims("first"),
+        claims("second"),
+        Test {
+            resources: &["spi1", "uart0"],
+            ..claims("third")
+        },
+        Test {
+            test: TestFn::Plain(beside_uart0),
+            ..plain("unclaimed")
+        },
+        claims("fourth"),
+    ];
+    let config = RunConfig {
+        test_threads: Some(4),
+        ..RunConfig::default()
+    };
+    let results = test_all(&config, &mut Events::default(), tests);
+    assert_eq!(results.to_string(), "test result: ok. 5 passed; 0 failed; 0 skipped; 0 filtered out");
+    assert_eq!(SAW_UART0.load(Ordering::SeqCst), 1, "unclaimed didn't run alongside uart0");
+}
+
+#[test]
+fn order() {
+    let tests = vec![
+        Test {
+            module_path: "uart",
+            ..plain("loopback")
+        },
+        plain("crc"),
+        Test {
+            module_path: "spi",
+            ..plain("transfer")
+        },
+        plain("alloc"),
+        plain("bits"),
+    ];
+    let names = |order, tests: &[Test]| {
+        let mut events = Events::default();
+        test_all(&RunConfig {
+            order,
+            ..RunConfig::default()
+        }, &mut events, tests);
+        events.0.iter().filter_map(|e| e.strip_prefix("ok ")).map(String::from).collect::<Vec<_>>()
+    };
+    assert_eq!(names(TestOrder::Given, &tests), ["loopback", "crc", "transfer", "alloc", "bits"]);
+    // by module path first, which is `runner` for the rest
+    assert_eq!(names(TestOrder::Sorted, &tests), ["alloc", "bits", "crc", "transfer", "loopback"]);
+
+    let shuffled = names(TestOrder::Shuffled(1), &tests);
+    assert_eq!(names(TestOrder::Shuffled(1), &tests), shuffled);
+    // where the tests start out doesn't matter
+    let reversed: Vec<_> = tests.iter().rev().cloned().collect();
+    assert_eq!(names(TestOrder::Shuffled(1), &reversed), shuffled);
+    let mut sorted = shuffled.clone();
+    sorted.sort();
+    assert_eq!(sorted, ["alloc", "bits", "crc", "loopback", "transfer"]);
+    assert!((2..10).any(|seed| names(TestOrder::Shuffled(seed), &tests) != shuffled));
+
+    let mut out = String::new();
+    test_all(&RunConfig {
+        order: TestOrder::Shuffled(1),
+        ..RunConfig::default()
+    }, &mut PrettyReporter::new(&mut out), &tests);
+    assert!(out.starts_with("running 5 tests (shuffled with seed 1)\n"));
+}